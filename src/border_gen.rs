@@ -0,0 +1,117 @@
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use noise::{NoiseFn, Perlin};
+
+use effigy_shared::game::map::{
+    MapGrid, MapTileIndex, MapTileVisualType, MapTileXY, SquareBounds, MAP_CHUNK_TILES_LENGTH,
+};
+
+/// Tiles per unit of the noise roughening the inner edge of a natural border
+const BORDER_NOISE_TILES_PER_UNIT: f64 = 8.0;
+
+/// What fills the border of the map
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BorderStyle {
+    /// The map falls off into water, like an island or continent
+    Ocean,
+    /// A ring of impassable terrain with a ragged inner edge, like a mountain range
+    Terrain,
+    /// A wall of impassable terrain with a straight inner edge, which only changes thickness from
+    /// one chunk to the next
+    Wall,
+}
+
+/// Outline of the playable area inside the border
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BorderShape {
+    /// The border follows the map edges
+    Square,
+    /// The border follows the largest circle fitting in the map, filling the corners
+    Radial,
+}
+
+/// Border around a bounded map.
+/// See: `MapGenerator::with_border`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapBorder {
+    pub style: BorderStyle,
+    pub shape: BorderShape,
+    /// Thickness of the border in tiles, before noise is applied
+    pub width: u32,
+    /// How far the inner edge of the border strays from its shape, as a fraction of `width`,
+    /// between 0.0 and 1.0 (inclusive)
+    pub noisiness: f64,
+}
+
+/// Fills every tile within the border with the border's tile type, overriding any layer.
+///
+/// The inner edge of the border is offset by Perlin noise sampled at world tile coordinates, by up
+/// to `noisiness * width` tiles in either direction. Walls sample the noise once per chunk instead
+/// of once per tile.
+pub(crate) fn apply_map_border(map_grid: &mut MapGrid, seed: u32, border: &MapBorder) {
+    assert!(
+        border.noisiness >= 0.0 && border.noisiness <= 1.0,
+        "Noisiness value needs to be between 0.0 and 1.0 (inclusive)"
+    );
+
+    info!("Applying map border: {:?} ...", border);
+    let func_start_time = std::time::Instant::now();
+
+    let width = map_grid.get_tiles_bounds().x as usize;
+    let height = map_grid.get_tiles_bounds().y as usize;
+    let border_type = match border.style {
+        BorderStyle::Ocean => MapTileVisualType::Water,
+        BorderStyle::Terrain | BorderStyle::Wall => MapTileVisualType::Terrain,
+    };
+
+    let perlin = Perlin::new(seed);
+    let chunk_length = MAP_CHUNK_TILES_LENGTH as usize;
+    let max_offset = border.noisiness * border.width as f64;
+    let center = ((width as f64 - 1.0) / 2.0, (height as f64 - 1.0) / 2.0);
+    let radius = (width.min(height) as f64 - 1.0) / 2.0;
+
+    let mut border_count: usize = 0;
+    for y in 0..height {
+        for x in 0..width {
+            // Distance inwards from the outline of the map
+            let distance = match border.shape {
+                BorderShape::Square => x.min(y).min(width - 1 - x).min(height - 1 - y) as f64,
+                BorderShape::Radial => {
+                    radius - ((x as f64 - center.0).powi(2) + (y as f64 - center.1).powi(2)).sqrt()
+                }
+            };
+            let noise_point = match border.style {
+                BorderStyle::Ocean | BorderStyle::Terrain => [
+                    x as f64 / BORDER_NOISE_TILES_PER_UNIT,
+                    y as f64 / BORDER_NOISE_TILES_PER_UNIT,
+                ],
+                // Halfway between lattice points, where Perlin noise isn't always zero
+                BorderStyle::Wall => [
+                    (x / chunk_length) as f64 + 0.5,
+                    (y / chunk_length) as f64 + 0.5,
+                ],
+            };
+            let offset = perlin.get(noise_point) * max_offset;
+            if distance + offset >= border.width as f64 {
+                continue;
+            }
+
+            map_grid
+                .get_tile_mut(&MapTileXY {
+                    x: x as MapTileIndex,
+                    y: y as MapTileIndex,
+                })
+                .unwrap()
+                .set_visual_type(&border_type)
+                .set_walkability_blocked(&SquareBounds::ALL);
+            border_count += 1;
+        }
+    }
+
+    info!(
+        "... done applying map border to {} tiles! Completed in {:.3} seconds",
+        border_count,
+        func_start_time.elapsed().as_secs_f32()
+    );
+}
@@ -0,0 +1,87 @@
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use effigy_shared::game::map::{MapChunkXY, MapGrid, MapTileIndex, MapTileVisualType, MapTileXY};
+
+use super::border_gen::{BorderShape, BorderStyle, MapBorder};
+use super::map_gen::MapGenerator;
+
+fn new_generator() -> MapGenerator {
+    MapGenerator::new(453537, &MapChunkXY { x: 4, y: 4 })
+        .with_terrain(0.2, 0.5)
+        .with_trees(0.2, 0.5)
+}
+
+fn generate_with_border(border: MapBorder) -> MapGrid {
+    new_generator().with_border(border).generate()
+}
+
+fn get_visual_type(grid: &MapGrid, x: MapTileIndex, y: MapTileIndex) -> MapTileVisualType {
+    grid.get_tile(&MapTileXY { x, y })
+        .unwrap()
+        .visual_type
+        .clone()
+}
+
+#[test]
+fn square_border_without_noise() {
+    let grid = generate_with_border(MapBorder {
+        style: BorderStyle::Wall,
+        shape: BorderShape::Square,
+        width: 3,
+        noisiness: 0.0,
+    });
+    let borderless_grid = new_generator().generate();
+    let width = grid.get_tiles_bounds().x;
+    let height = grid.get_tiles_bounds().y;
+    for y in 0..height {
+        for x in 0..width {
+            let distance = x.min(y).min(width - 1 - x).min(height - 1 - y);
+            if distance < 3 {
+                assert_eq!(get_visual_type(&grid, x, y), MapTileVisualType::Terrain);
+            } else {
+                assert_eq!(
+                    get_visual_type(&grid, x, y),
+                    get_visual_type(&borderless_grid, x, y)
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn radial_ocean_border() {
+    let grid = generate_with_border(MapBorder {
+        style: BorderStyle::Ocean,
+        shape: BorderShape::Radial,
+        width: 8,
+        noisiness: 0.5,
+    });
+    let width = grid.get_tiles_bounds().x;
+    let height = grid.get_tiles_bounds().y;
+
+    // Corners are beyond the circle, the center is well within it
+    for (x, y) in [
+        (0, 0),
+        (width - 1, 0),
+        (0, height - 1),
+        (width - 1, height - 1),
+    ] {
+        assert_eq!(get_visual_type(&grid, x, y), MapTileVisualType::Water);
+    }
+    assert_ne!(
+        get_visual_type(&grid, width / 2, height / 2),
+        MapTileVisualType::Water
+    );
+
+    // Noise makes the shoreline vary in distance from the map edge
+    let shoreline_distances: Vec<MapTileIndex> = (0..height)
+        .step_by(8)
+        .filter_map(|y| {
+            (0..width).find(|&x| get_visual_type(&grid, x, y) != MapTileVisualType::Water)
+        })
+        .collect();
+    assert!(shoreline_distances
+        .windows(2)
+        .any(|pair| pair[0] != pair[1]));
+}
@@ -0,0 +1,359 @@
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use std::collections::VecDeque;
+
+use effigy_shared::game::map::{MapGrid, MapTileIndex, MapTileVisualType, MapTileXY};
+
+use super::grid_index::{
+    get_neighbor_edges, get_tile_at_index, get_tile_at_index_mut, get_tile_xy_at_index, Edge,
+    NO_BLOCKED_EDGES,
+};
+use super::random::hash_coords;
+
+pub type BuildingId = u32;
+
+/// How `MapGenerator::generate` cleans up the raw footprints of the building layer
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildingShapeOptions {
+    /// Buildings with fewer tiles than this are removed
+    pub min_area: usize,
+    /// Shrink every footprint to the largest rectangle that fits inside it
+    pub rectangular: bool,
+    /// Number of door edges to open onto neighboring land, where possible. At least 1.
+    pub doors: usize,
+}
+impl Default for BuildingShapeOptions {
+    fn default() -> Self {
+        BuildingShapeOptions {
+            min_area: 4,
+            rectangular: false,
+            doors: 1,
+        }
+    }
+}
+
+/// A single building of the map
+#[derive(Debug)]
+pub struct Building {
+    pub id: BuildingId,
+    /// Tiles of the building, in scan order
+    pub footprint: Vec<MapTileXY>,
+    /// Tiles of the building with a door edge opening onto neighboring land
+    pub doors: Vec<MapTileXY>,
+    /// Lower-left corner of the bounding box (inclusive)
+    pub bounds_min: MapTileXY,
+    /// Upper-right corner of the bounding box (inclusive)
+    pub bounds_max: MapTileXY,
+}
+
+/// Every building of a generated map, with a lookup from tile to building
+pub struct MapBuildings {
+    tiles_bounds: MapTileXY,
+    /// Building ID per tile, row-major
+    tile_building_ids: Vec<Option<BuildingId>>,
+    buildings: Vec<Building>,
+}
+impl MapBuildings {
+    /// Returns the ID of the building on the tile, or `None` if there is no building or the tile
+    /// is out of bounds
+    pub fn get_building_id(&self, tile_xy: &MapTileXY) -> Option<BuildingId> {
+        if tile_xy.x >= self.tiles_bounds.x || tile_xy.y >= self.tiles_bounds.y {
+            return None;
+        }
+        let index = (tile_xy.y as usize) * (self.tiles_bounds.x as usize) + (tile_xy.x as usize);
+        self.tile_building_ids[index]
+    }
+
+    pub fn get_building(&self, building_id: BuildingId) -> Option<&Building> {
+        self.buildings.get(building_id as usize)
+    }
+
+    /// Returns the building on the tile, or `None` if there is no building or the tile is out of
+    /// bounds
+    pub fn get_building_at(&self, tile_xy: &MapTileXY) -> Option<&Building> {
+        self.get_building_id(tile_xy)
+            .and_then(|building_id| self.get_building(building_id))
+    }
+
+    /// All buildings, ordered by ID. IDs are assigned in scan order of each building's first tile.
+    pub fn get_buildings(&self) -> &[Building] {
+        &self.buildings
+    }
+}
+
+/// Labels every building of the map.
+///
+/// A building is a 4-connected set of building tiles from the same cell of the building noise,
+/// given per tile by `cell_keys`, so two touching cells are two different buildings. Building
+/// tiles without a cell key (e.g. not placed by the building layer) are grouped by connectivity
+/// alone.
+pub(crate) fn label_buildings(map_grid: &MapGrid, cell_keys: &[Option<u64>]) -> MapBuildings {
+    let width = map_grid.get_tiles_bounds().x as usize;
+    let height = map_grid.get_tiles_bounds().y as usize;
+    assert_eq!(
+        cell_keys.len(),
+        width * height,
+        "Expected a building cell key per tile of the map"
+    );
+
+    let is_building = |index: usize| {
+        get_tile_at_index(map_grid, width, index).visual_type == MapTileVisualType::Building
+    };
+
+    let mut tile_building_ids: Vec<Option<BuildingId>> = vec![None; width * height];
+    let mut buildings: Vec<Building> = Vec::new();
+    let mut queue: VecDeque<usize> = VecDeque::new();
+    for start_index in 0..(width * height) {
+        if tile_building_ids[start_index].is_some() || !is_building(start_index) {
+            continue;
+        }
+        let building_id = buildings.len() as BuildingId;
+        let mut tile_indices: Vec<usize> = Vec::new();
+        tile_building_ids[start_index] = Some(building_id);
+        queue.push_back(start_index);
+        while let Some(index) = queue.pop_front() {
+            tile_indices.push(index);
+            for (neighbor_index, _) in get_neighbor_edges(width, height, index) {
+                if tile_building_ids[neighbor_index].is_none()
+                    && is_building(neighbor_index)
+                    && cell_keys[neighbor_index] == cell_keys[start_index]
+                {
+                    tile_building_ids[neighbor_index] = Some(building_id);
+                    queue.push_back(neighbor_index);
+                }
+            }
+        }
+        tile_indices.sort_unstable();
+
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (width, height, 0, 0);
+        for &index in &tile_indices {
+            min_x = min_x.min(index % width);
+            min_y = min_y.min(index / width);
+            max_x = max_x.max(index % width);
+            max_y = max_y.max(index / width);
+        }
+        buildings.push(Building {
+            id: building_id,
+            footprint: tile_indices
+                .iter()
+                .map(|&index| get_tile_xy_at_index(width, index))
+                .collect(),
+            doors: Vec::new(),
+            bounds_min: MapTileXY {
+                x: min_x as MapTileIndex,
+                y: min_y as MapTileIndex,
+            },
+            bounds_max: MapTileXY {
+                x: max_x as MapTileIndex,
+                y: max_y as MapTileIndex,
+            },
+        });
+    }
+
+    MapBuildings {
+        tiles_bounds: map_grid.get_tiles_bounds(),
+        tile_building_ids,
+        buildings,
+    }
+}
+
+/// Removes buildings below the minimum area and optionally rectangularizes the rest. Removed
+/// tiles turn back into land.
+///
+/// This runs before walkability blockage is simplified, while building tiles are fully blocked.
+pub(crate) fn shape_buildings(
+    map_grid: &mut MapGrid,
+    cell_keys: &[Option<u64>],
+    options: &BuildingShapeOptions,
+) {
+    info!("Shaping buildings: {:?} ...", options);
+    let func_start_time = std::time::Instant::now();
+
+    let width = map_grid.get_tiles_bounds().x as usize;
+
+    let map_buildings = label_buildings(map_grid, cell_keys);
+    let mut removed_count: usize = 0;
+    // Tiles kept of the building being shaped, cleared again after each building
+    let mut is_kept = vec![false; map_grid.get_tiles_count()];
+    for building in map_buildings.get_buildings() {
+        let building_indices: Vec<usize> = building
+            .footprint
+            .iter()
+            .map(|tile_xy| (tile_xy.y as usize) * width + (tile_xy.x as usize))
+            .collect();
+
+        let kept_indices: Vec<usize> = if options.rectangular {
+            get_largest_rectangle_indices(width, building, &building_indices)
+        } else {
+            building_indices.clone()
+        };
+        let kept_indices = if kept_indices.len() < options.min_area {
+            Vec::new()
+        } else {
+            kept_indices
+        };
+
+        for &index in &kept_indices {
+            is_kept[index] = true;
+        }
+        for index in building_indices {
+            if !is_kept[index] {
+                get_tile_at_index_mut(map_grid, width, index)
+                    .set_visual_type(&MapTileVisualType::Land)
+                    .set_walkability_blocked(&NO_BLOCKED_EDGES);
+                removed_count += 1;
+            }
+        }
+        for &index in &kept_indices {
+            is_kept[index] = false;
+        }
+    }
+
+    info!(
+        "... done shaping buildings, removed {} tiles! Completed in {:.3} seconds",
+        removed_count,
+        func_start_time.elapsed().as_secs_f32()
+    );
+}
+
+/// Blocks the shared edges between tiles of different buildings, which were opened when
+/// simplifying walkability blockage since they share a visual type
+pub(crate) fn separate_buildings(map_grid: &mut MapGrid, map_buildings: &MapBuildings) {
+    let width = map_grid.get_tiles_bounds().x as usize;
+    let height = map_grid.get_tiles_bounds().y as usize;
+
+    for index in 0..(width * height) {
+        let building_id = match map_buildings.tile_building_ids[index] {
+            Some(building_id) => building_id,
+            None => continue,
+        };
+        for (neighbor_index, edge) in get_neighbor_edges(width, height, index) {
+            match map_buildings.tile_building_ids[neighbor_index] {
+                Some(neighbor_building_id) if neighbor_building_id != building_id => {
+                    set_edge_blocked(map_grid, width, index, edge, true);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Opens door edges on the outside of every building, each facing a land tile, and records the
+/// door tiles on each building. Doors are picked by hashing the seed with each candidate edge, so
+/// the same map always has the same doors.
+///
+/// This runs after walkability blockage is simplified, so the building interior is already open.
+pub(crate) fn place_building_doors(
+    map_grid: &mut MapGrid,
+    map_buildings: &mut MapBuildings,
+    seed: u32,
+    options: &BuildingShapeOptions,
+) {
+    assert!(options.doors >= 1, "Buildings need at least 1 door");
+
+    let width = map_grid.get_tiles_bounds().x as usize;
+    let height = map_grid.get_tiles_bounds().y as usize;
+
+    for building in map_buildings.buildings.iter_mut() {
+        let mut door_candidates: Vec<(u64, usize, usize, Edge)> = Vec::new();
+        for tile_xy in &building.footprint {
+            let index = (tile_xy.y as usize) * width + (tile_xy.x as usize);
+            for (neighbor_index, edge) in get_neighbor_edges(width, height, index) {
+                if get_tile_at_index(map_grid, width, neighbor_index).visual_type
+                    == MapTileVisualType::Land
+                {
+                    let hash = hash_coords(seed, index as i64, edge as i64);
+                    door_candidates.push((hash, index, neighbor_index, edge));
+                }
+            }
+        }
+        if door_candidates.is_empty() {
+            warn!(
+                "Building {} at {:?} has no neighboring land for a door",
+                building.id, building.bounds_min
+            );
+        }
+        door_candidates.sort_unstable_by_key(|(hash, index, _, _)| (*hash, *index));
+
+        for (_, index, neighbor_index, edge) in door_candidates.into_iter().take(options.doors) {
+            set_edge_blocked(map_grid, width, index, edge, false);
+            set_edge_blocked(map_grid, width, neighbor_index, edge.opposite(), false);
+            // A tile with several door edges is still a single door tile
+            let door_xy = get_tile_xy_at_index(width, index);
+            if !building.doors.contains(&door_xy) {
+                building.doors.push(door_xy);
+            }
+        }
+    }
+}
+
+fn set_edge_blocked(map_grid: &mut MapGrid, width: usize, index: usize, edge: Edge, blocked: bool) {
+    let tile = get_tile_at_index_mut(map_grid, width, index);
+    match edge {
+        Edge::Left => tile.walkability_blocked.left = blocked,
+        Edge::Right => tile.walkability_blocked.right = blocked,
+        Edge::Down => tile.walkability_blocked.down = blocked,
+        Edge::Up => tile.walkability_blocked.up = blocked,
+    }
+}
+
+/// Finds the largest axis-aligned rectangle of tiles within a building, preferring the first found
+/// in scan order on ties
+fn get_largest_rectangle_indices(
+    width: usize,
+    building: &Building,
+    building_indices: &[usize],
+) -> Vec<usize> {
+    let min_x = building.bounds_min.x as usize;
+    let min_y = building.bounds_min.y as usize;
+    let box_width = building.bounds_max.x as usize - min_x + 1;
+    let box_height = building.bounds_max.y as usize - min_y + 1;
+
+    let mut is_in_building = vec![false; box_width * box_height];
+    for &index in building_indices {
+        is_in_building[(index / width - min_y) * box_width + (index % width - min_x)] = true;
+    }
+
+    // Histogram of consecutive building tiles ending at each row, scanning rows upwards
+    let mut column_heights = vec![0usize; box_width];
+    // (area, left, bottom, rectangle width, rectangle height)
+    let mut largest: (usize, usize, usize, usize, usize) = (0, 0, 0, 0, 0);
+    for y in 0..box_height {
+        for x in 0..box_width {
+            column_heights[x] = if is_in_building[y * box_width + x] {
+                column_heights[x] + 1
+            } else {
+                0
+            };
+        }
+        for left in 0..box_width {
+            let mut rectangle_height = usize::MAX;
+            for (right, &column_height) in column_heights.iter().enumerate().skip(left) {
+                rectangle_height = rectangle_height.min(column_height);
+                if rectangle_height == 0 {
+                    break;
+                }
+                let area = (right - left + 1) * rectangle_height;
+                if area > largest.0 {
+                    largest = (
+                        area,
+                        left,
+                        y + 1 - rectangle_height,
+                        right - left + 1,
+                        rectangle_height,
+                    );
+                }
+            }
+        }
+    }
+
+    let (_, left, bottom, rectangle_width, rectangle_height) = largest;
+    let mut indices = Vec::with_capacity(rectangle_width * rectangle_height);
+    for y in (min_y + bottom)..(min_y + bottom + rectangle_height) {
+        for x in (min_x + left)..(min_x + left + rectangle_width) {
+            indices.push(y * width + x);
+        }
+    }
+    indices
+}
@@ -0,0 +1,129 @@
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use effigy_shared::game::map::{MapChunkXY, MapGrid, MapTileVisualType, MapTileXY};
+
+use super::building_gen::{label_buildings, place_building_doors, BuildingShapeOptions};
+use super::map_gen::{simplify_map_walkability_blockage, MapGenerator};
+use super::noise_gen::BuildingNoiseStyle;
+use super::walkability::WalkabilityRules;
+
+#[test]
+fn shape_rectangular_buildings_with_doors() {
+    let options = BuildingShapeOptions {
+        min_area: 6,
+        rectangular: true,
+        doors: 1,
+    };
+    let generated = MapGenerator::new(453537, &MapChunkXY { x: 4, y: 4 })
+        .with_buildings(0.5, 0.25)
+        .with_building_shape(options)
+        .generate_full();
+    let grid = &generated.map_grid;
+    let buildings = generated.buildings.get_buildings();
+    assert!(!buildings.is_empty(), "Expected at least one building");
+
+    for building in buildings {
+        assert!(building.footprint.len() >= 6);
+
+        // Rectangular footprints fill their bounding box
+        let bounds_area = ((building.bounds_max.x - building.bounds_min.x + 1)
+            * (building.bounds_max.y - building.bounds_min.y + 1))
+            as usize;
+        assert_eq!(building.footprint.len(), bounds_area);
+
+        // Every door has an unblocked edge leading out onto land
+        assert_eq!(building.doors.len(), 1, "Expected a door on {:?}", building);
+        let door = &building.doors[0];
+        let blocked = &grid.get_tile(door).unwrap().walkability_blocked;
+        let is_land = |x, y| {
+            grid.get_tile(&MapTileXY { x, y })
+                .map_or(false, |tile| tile.visual_type == MapTileVisualType::Land)
+        };
+        assert!(
+            (!blocked.left && door.x > 0 && is_land(door.x - 1, door.y))
+                || (!blocked.right && is_land(door.x + 1, door.y))
+                || (!blocked.down && door.y > 0 && is_land(door.x, door.y - 1))
+                || (!blocked.up && is_land(door.x, door.y + 1))
+        );
+    }
+}
+
+#[test]
+fn touching_buildings_stay_distinct() {
+    let generated = MapGenerator::new(453537, &MapChunkXY { x: 4, y: 4 })
+        .with_buildings(1.0, 0.25)
+        .generate_full();
+    let grid = &generated.map_grid;
+    let map_buildings = &generated.buildings;
+
+    let mut touching_count: usize = 0;
+    for building in map_buildings.get_buildings() {
+        for tile_xy in &building.footprint {
+            assert_eq!(map_buildings.get_building_id(tile_xy), Some(building.id));
+
+            let right_xy = MapTileXY {
+                x: tile_xy.x + 1,
+                y: tile_xy.y,
+            };
+            match map_buildings.get_building_id(&right_xy) {
+                Some(right_id) if right_id != building.id => {
+                    touching_count += 1;
+                    assert!(grid.get_tile(tile_xy).unwrap().walkability_blocked.right);
+                    assert!(grid.get_tile(&right_xy).unwrap().walkability_blocked.left);
+                }
+                Some(_) => {
+                    assert!(!grid.get_tile(tile_xy).unwrap().walkability_blocked.right);
+                }
+                None => {}
+            }
+        }
+    }
+    assert!(touching_count > 0, "Expected some buildings to touch");
+}
+
+#[test]
+fn styled_buildings_form_huts() {
+    let generated = MapGenerator::new(453537, &MapChunkXY { x: 4, y: 4 })
+        .with_styled_buildings(0.6, 0.5, &BuildingNoiseStyle::ROUNDED_HUTS)
+        .generate_full();
+    let buildings = generated.buildings.get_buildings();
+    assert!(!buildings.is_empty(), "Expected at least one hut");
+
+    // Huts are blobs around each cell's seed point, so they never span a whole cell
+    for building in buildings {
+        assert!(building.bounds_max.x - building.bounds_min.x < 6);
+        assert!(building.bounds_max.y - building.bounds_min.y < 6);
+    }
+}
+
+#[test]
+fn single_tile_building_with_two_doors() {
+    let mut grid = MapGrid::new(&MapChunkXY { x: 1, y: 1 });
+    let width = grid.get_tiles_bounds().x as usize;
+    let tile_xy = MapTileXY { x: 5, y: 5 };
+    grid.get_tile_mut(&tile_xy)
+        .unwrap()
+        .set_visual_type(&MapTileVisualType::Building);
+    let mut cell_keys = vec![None; grid.get_tiles_count()];
+    cell_keys[5 * width + 5] = Some(1);
+    simplify_map_walkability_blockage(&mut grid, &WalkabilityRules::default(), false);
+
+    let mut buildings = label_buildings(&grid, &cell_keys);
+    let options = BuildingShapeOptions {
+        doors: 2,
+        ..BuildingShapeOptions::default()
+    };
+    place_building_doors(&mut grid, &mut buildings, 453537, &options);
+
+    // Both door edges open from the same tile, which is listed once
+    let building = buildings.get_building_at(&tile_xy).unwrap();
+    assert_eq!(building.doors.len(), 1);
+    assert_eq!(building.doors[0], tile_xy);
+    let blocked = &grid.get_tile(&tile_xy).unwrap().walkability_blocked;
+    let open_sides_count = [blocked.left, blocked.right, blocked.down, blocked.up]
+        .iter()
+        .filter(|is_blocked| !**is_blocked)
+        .count();
+    assert_eq!(open_sides_count, 2);
+}
@@ -0,0 +1,386 @@
+//! Compact binary encoding of the tiles of a single `MapChunk`, for sending edited chunks to
+//! clients which can't regenerate them.
+//!
+//! Layout, with integers little-endian:
+//! - `u8` encoding version, see `CHUNK_ENCODING_VERSION`
+//! - `u32` chunk x and `u32` chunk y
+//! - `u8` palette length, followed by one `u8` tile state per palette entry
+//! - `u8` body mode, followed by the body:
+//!   - `BODY_MODE_BITPACKED`: the palette index of every tile in scan order, packed into as few
+//!     bits per tile as the palette needs, least significant bit first
+//!   - `BODY_MODE_RUN_LENGTH`: runs of tiles in scan order, each a `u8` palette index followed by
+//!     a LEB128 run length
+//!
+//! A tile state packs the visual type into the lowest 3 bits, followed by the blocked flags of the
+//! left, right, down and up edges. A flag only blocks units leaving the tile across that edge, so
+//! both tiles' flags are needed to tell whether an edge is one-way.
+
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use std::fmt;
+
+use effigy_shared::game::map::{
+    MapChunkXY, MapGrid, MapTileIndex, MapTileVisualType, MapTileXY, SquareBounds,
+    MAP_CHUNK_TILES_LENGTH,
+};
+
+/// Version written to the header of every encoded chunk. Bump whenever the layout changes.
+pub const CHUNK_ENCODING_VERSION: u8 = 1;
+
+const BODY_MODE_BITPACKED: u8 = 0;
+const BODY_MODE_RUN_LENGTH: u8 = 1;
+
+pub(crate) const CHUNK_TILES_COUNT: usize =
+    (MAP_CHUNK_TILES_LENGTH as usize) * (MAP_CHUNK_TILES_LENGTH as usize);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChunkDecodeError {
+    /// The data was encoded with a version this decoder doesn't support
+    UnsupportedVersion(u8),
+    /// The data ended before the chunk was complete
+    UnexpectedEnd,
+    /// The data continued after the chunk was complete
+    TrailingBytes,
+    /// The chunk isn't within the bounds of the map being decoded into
+    ChunkOutOfBounds {
+        x: u32,
+        y: u32,
+    },
+    InvalidTileState(u8),
+    InvalidPaletteIndex(u32),
+    InvalidBodyMode(u8),
+    /// A variable-length integer was longer than 32 bits
+    InvalidVarint,
+    /// The tiles of the body didn't add up to a whole chunk
+    InvalidTilesCount(usize),
+    /// A tile index was beyond the end of the chunk
+    InvalidTileIndex(usize),
+}
+impl fmt::Display for ChunkDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkDecodeError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported chunk encoding version {}, expected {}",
+                version, CHUNK_ENCODING_VERSION
+            ),
+            ChunkDecodeError::UnexpectedEnd => write!(f, "unexpected end of chunk data"),
+            ChunkDecodeError::TrailingBytes => write!(f, "unexpected bytes after chunk data"),
+            ChunkDecodeError::ChunkOutOfBounds { x, y } => {
+                write!(f, "chunk ({}, {}) is out of bounds of the map", x, y)
+            }
+            ChunkDecodeError::InvalidTileState(state) => write!(f, "invalid tile state {}", state),
+            ChunkDecodeError::InvalidPaletteIndex(index) => {
+                write!(f, "invalid palette index {}", index)
+            }
+            ChunkDecodeError::InvalidBodyMode(mode) => write!(f, "invalid body mode {}", mode),
+            ChunkDecodeError::InvalidVarint => write!(f, "variable-length integer is too long"),
+            ChunkDecodeError::InvalidTilesCount(count) => write!(
+                f,
+                "expected {} tiles in chunk, found {}",
+                CHUNK_TILES_COUNT, count
+            ),
+            ChunkDecodeError::InvalidTileIndex(index) => write!(
+                f,
+                "tile index {} is beyond the {} tiles of a chunk",
+                index, CHUNK_TILES_COUNT
+            ),
+        }
+    }
+}
+impl std::error::Error for ChunkDecodeError {}
+
+/// Encodes every tile of a chunk of the map, choosing whichever of bitpacking or run-length
+/// encoding is smaller
+pub fn encode_chunk(map_grid: &MapGrid, chunk_xy: &MapChunkXY) -> Vec<u8> {
+    let tile_states = get_chunk_tile_states(map_grid, chunk_xy);
+
+    // Palette in order of first appearance, so the same chunk always encodes the same way
+    let mut palette: Vec<u8> = Vec::new();
+    let palette_indices: Vec<u8> = tile_states
+        .iter()
+        .map(
+            |state| match palette.iter().position(|entry| entry == state) {
+                Some(index) => index as u8,
+                None => {
+                    palette.push(*state);
+                    (palette.len() - 1) as u8
+                }
+            },
+        )
+        .collect();
+
+    let bitpacked_body = encode_bitpacked(&palette_indices, get_bits_per_index(palette.len()));
+    let run_length_body = encode_run_length(&palette_indices);
+
+    let mut bytes: Vec<u8> =
+        Vec::with_capacity(11 + palette.len() + bitpacked_body.len().min(run_length_body.len()));
+    bytes.push(CHUNK_ENCODING_VERSION);
+    bytes.extend_from_slice(&(chunk_xy.x as u32).to_le_bytes());
+    bytes.extend_from_slice(&(chunk_xy.y as u32).to_le_bytes());
+    bytes.push(palette.len() as u8);
+    bytes.extend_from_slice(&palette);
+    if bitpacked_body.len() <= run_length_body.len() {
+        bytes.push(BODY_MODE_BITPACKED);
+        bytes.extend_from_slice(&bitpacked_body);
+    } else {
+        bytes.push(BODY_MODE_RUN_LENGTH);
+        bytes.extend_from_slice(&run_length_body);
+    }
+    bytes
+}
+
+/// Decodes a chunk encoded by `encode_chunk` into the map, returning which chunk it was.
+///
+/// The map is only modified if the whole chunk decodes successfully.
+pub fn decode_chunk(map_grid: &mut MapGrid, bytes: &[u8]) -> Result<MapChunkXY, ChunkDecodeError> {
+    let mut reader = ByteReader::new(bytes);
+    let version = reader.read_u8()?;
+    if version != CHUNK_ENCODING_VERSION {
+        return Err(ChunkDecodeError::UnsupportedVersion(version));
+    }
+    let chunk_x = reader.read_u32()?;
+    let chunk_y = reader.read_u32()?;
+    let chunks_bounds = map_grid.get_chunks_bounds();
+    if chunk_x >= chunks_bounds.x as u32 || chunk_y >= chunks_bounds.y as u32 {
+        return Err(ChunkDecodeError::ChunkOutOfBounds {
+            x: chunk_x,
+            y: chunk_y,
+        });
+    }
+
+    let palette_length = reader.read_u8()? as usize;
+    let mut palette: Vec<(MapTileVisualType, SquareBounds)> = Vec::with_capacity(palette_length);
+    for _ in 0..palette_length {
+        palette.push(decode_tile_state(reader.read_u8()?)?);
+    }
+
+    let palette_indices = match reader.read_u8()? {
+        BODY_MODE_BITPACKED => decode_bitpacked(&mut reader, get_bits_per_index(palette_length))?,
+        BODY_MODE_RUN_LENGTH => decode_run_length(&mut reader)?,
+        mode => return Err(ChunkDecodeError::InvalidBodyMode(mode)),
+    };
+    reader.finish()?;
+    if let Some(&index) = palette_indices
+        .iter()
+        .find(|&&index| index as usize >= palette.len())
+    {
+        return Err(ChunkDecodeError::InvalidPaletteIndex(index));
+    }
+
+    let chunk_xy = MapChunkXY {
+        x: chunk_x as _,
+        y: chunk_y as _,
+    };
+    for (local_index, &palette_index) in palette_indices.iter().enumerate() {
+        let (visual_type, walkability_blocked) = &palette[palette_index as usize];
+        map_grid
+            .get_tile_mut(&get_chunk_tile_xy(&chunk_xy, local_index))
+            .unwrap()
+            .set_visual_type(visual_type)
+            .set_walkability_blocked(walkability_blocked);
+    }
+    Ok(chunk_xy)
+}
+
+/// World position of a tile of a chunk, by its scan-order index within the chunk
+pub(crate) fn get_chunk_tile_xy(chunk_xy: &MapChunkXY, local_index: usize) -> MapTileXY {
+    MapTileXY {
+        x: (chunk_xy.x as MapTileIndex) * MAP_CHUNK_TILES_LENGTH
+            + (local_index % MAP_CHUNK_TILES_LENGTH as usize) as MapTileIndex,
+        y: (chunk_xy.y as MapTileIndex) * MAP_CHUNK_TILES_LENGTH
+            + (local_index / MAP_CHUNK_TILES_LENGTH as usize) as MapTileIndex,
+    }
+}
+
+/// Tile states of every tile of a chunk, in scan order
+pub(crate) fn get_chunk_tile_states(map_grid: &MapGrid, chunk_xy: &MapChunkXY) -> Vec<u8> {
+    let chunks_bounds = map_grid.get_chunks_bounds();
+    assert!(
+        chunk_xy.x < chunks_bounds.x && chunk_xy.y < chunks_bounds.y,
+        "Chunk {:?} is out of bounds of the map",
+        chunk_xy
+    );
+
+    (0..CHUNK_TILES_COUNT)
+        .map(|local_index| {
+            let tile = map_grid
+                .get_tile(&get_chunk_tile_xy(chunk_xy, local_index))
+                .unwrap();
+            encode_tile_state(&tile.visual_type, &tile.walkability_blocked)
+        })
+        .collect()
+}
+
+pub(crate) const fn encode_tile_state(
+    visual_type: &MapTileVisualType,
+    walkability_blocked: &SquareBounds,
+) -> u8 {
+    let visual_type_bits: u8 = match visual_type {
+        MapTileVisualType::Land => 0,
+        MapTileVisualType::LandTree => 1,
+        MapTileVisualType::Water => 2,
+        MapTileVisualType::Terrain => 3,
+        MapTileVisualType::Building => 4,
+    };
+    visual_type_bits
+        | ((walkability_blocked.left as u8) << 3)
+        | ((walkability_blocked.right as u8) << 4)
+        | ((walkability_blocked.down as u8) << 5)
+        | ((walkability_blocked.up as u8) << 6)
+}
+
+pub(crate) fn decode_tile_state(
+    state: u8,
+) -> Result<(MapTileVisualType, SquareBounds), ChunkDecodeError> {
+    let visual_type = match state & 0b111 {
+        0 => MapTileVisualType::Land,
+        1 => MapTileVisualType::LandTree,
+        2 => MapTileVisualType::Water,
+        3 => MapTileVisualType::Terrain,
+        4 => MapTileVisualType::Building,
+        _ => return Err(ChunkDecodeError::InvalidTileState(state)),
+    };
+    if state & 0b1000_0000 != 0 {
+        return Err(ChunkDecodeError::InvalidTileState(state));
+    }
+    let walkability_blocked = SquareBounds {
+        left: state & (1 << 3) != 0,
+        right: state & (1 << 4) != 0,
+        down: state & (1 << 5) != 0,
+        up: state & (1 << 6) != 0,
+    };
+    Ok((visual_type, walkability_blocked))
+}
+
+/// Fewest bits that can hold every index of a palette, which is none for a single entry
+const fn get_bits_per_index(palette_length: usize) -> u32 {
+    if palette_length <= 1 {
+        0
+    } else {
+        usize::BITS - (palette_length - 1).leading_zeros()
+    }
+}
+
+fn encode_bitpacked(palette_indices: &[u8], bits_per_index: u32) -> Vec<u8> {
+    let mut bytes = vec![0u8; (palette_indices.len() * bits_per_index as usize).div_ceil(8)];
+    for (tile_index, &palette_index) in palette_indices.iter().enumerate() {
+        for bit in 0..bits_per_index as usize {
+            if palette_index & (1 << bit) != 0 {
+                let bit_index = tile_index * bits_per_index as usize + bit;
+                bytes[bit_index / 8] |= 1 << (bit_index % 8);
+            }
+        }
+    }
+    bytes
+}
+
+fn decode_bitpacked(
+    reader: &mut ByteReader,
+    bits_per_index: u32,
+) -> Result<Vec<u32>, ChunkDecodeError> {
+    let bytes = reader.read_bytes((CHUNK_TILES_COUNT * bits_per_index as usize).div_ceil(8))?;
+    Ok((0..CHUNK_TILES_COUNT)
+        .map(|tile_index| {
+            (0..bits_per_index as usize).fold(0u32, |palette_index, bit| {
+                let bit_index = tile_index * bits_per_index as usize + bit;
+                palette_index | ((((bytes[bit_index / 8] >> (bit_index % 8)) & 1) as u32) << bit)
+            })
+        })
+        .collect())
+}
+
+fn encode_run_length(palette_indices: &[u8]) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::new();
+    let mut runs = palette_indices.chunk_by(|a, b| a == b);
+    for run in &mut runs {
+        bytes.push(run[0]);
+        write_varint(&mut bytes, run.len() as u32);
+    }
+    bytes
+}
+
+fn decode_run_length(reader: &mut ByteReader) -> Result<Vec<u32>, ChunkDecodeError> {
+    let mut palette_indices: Vec<u32> = Vec::with_capacity(CHUNK_TILES_COUNT);
+    while palette_indices.len() < CHUNK_TILES_COUNT {
+        let palette_index = reader.read_u8()? as u32;
+        let run_length = reader.read_varint()? as usize;
+        if run_length == 0 || palette_indices.len() + run_length > CHUNK_TILES_COUNT {
+            return Err(ChunkDecodeError::InvalidTilesCount(
+                palette_indices.len() + run_length,
+            ));
+        }
+        palette_indices.extend(std::iter::repeat_n(palette_index, run_length));
+    }
+    Ok(palette_indices)
+}
+
+/// Writes an unsigned LEB128 integer
+pub(crate) fn write_varint(bytes: &mut Vec<u8>, mut value: u32) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+/// Reads the fields of encoded data in order, failing instead of reading past the end
+pub(crate) struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+impl<'a> ByteReader<'a> {
+    pub(crate) const fn new(bytes: &'a [u8]) -> Self {
+        ByteReader { bytes, position: 0 }
+    }
+
+    pub(crate) fn read_bytes(&mut self, count: usize) -> Result<&'a [u8], ChunkDecodeError> {
+        let end = self
+            .position
+            .checked_add(count)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or(ChunkDecodeError::UnexpectedEnd)?;
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    pub(crate) fn read_u8(&mut self) -> Result<u8, ChunkDecodeError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub(crate) fn read_u32(&mut self) -> Result<u32, ChunkDecodeError> {
+        Ok(u32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    /// Reads an unsigned LEB128 integer of at most 32 bits
+    pub(crate) fn read_varint(&mut self) -> Result<u32, ChunkDecodeError> {
+        let mut value: u32 = 0;
+        for shift in (0..35).step_by(7) {
+            let byte = self.read_u8()?;
+            // Only the low 4 bits of the 5th byte fit in 32 bits
+            if shift == 28 && byte & 0x70 != 0 {
+                return Err(ChunkDecodeError::InvalidVarint);
+            }
+            value |= ((byte & 0x7f) as u32) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(ChunkDecodeError::InvalidVarint)
+    }
+
+    /// Fails if any bytes are left unread
+    pub(crate) fn finish(&self) -> Result<(), ChunkDecodeError> {
+        if self.position == self.bytes.len() {
+            Ok(())
+        } else {
+            Err(ChunkDecodeError::TrailingBytes)
+        }
+    }
+}
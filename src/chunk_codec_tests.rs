@@ -0,0 +1,114 @@
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use effigy_shared::game::map::{MapChunkXY, MapGrid, MapTileXY, MAP_CHUNK_TILES_LENGTH};
+
+use super::chunk_codec::{
+    decode_chunk, encode_chunk, write_varint, ByteReader, ChunkDecodeError, CHUNK_ENCODING_VERSION,
+};
+use super::map_gen::MapGenerator;
+
+fn generate_example_map_grid() -> MapGrid {
+    MapGenerator::new(453537, &MapChunkXY { x: 2, y: 2 })
+        .with_terrain(0.2, 0.5)
+        .with_buildings(0.1, 0.25)
+        .with_water(0.2, 0.5)
+        .with_trees(0.2, 0.5)
+        .generate()
+}
+
+#[test]
+fn encode_chunk_round_trip() {
+    let grid = generate_example_map_grid();
+    let mut decoded_grid = MapGrid::new(&grid.get_chunks_bounds());
+    let chunks_bounds = grid.get_chunks_bounds();
+    for chunk_y in 0..chunks_bounds.y {
+        for chunk_x in 0..chunks_bounds.x {
+            let chunk_xy = MapChunkXY {
+                x: chunk_x,
+                y: chunk_y,
+            };
+            let bytes = encode_chunk(&grid, &chunk_xy);
+            assert_eq!(bytes[0], CHUNK_ENCODING_VERSION);
+            // A naive encoding takes a byte for the visual type and each edge flag of every tile
+            let naive_length = 5 * (MAP_CHUNK_TILES_LENGTH as usize).pow(2);
+            assert!(
+                bytes.len() * 4 < naive_length,
+                "Expected chunk {:?} to encode to under a quarter of {} bytes, got {}",
+                chunk_xy,
+                naive_length,
+                bytes.len()
+            );
+
+            assert_eq!(decode_chunk(&mut decoded_grid, &bytes), Ok(chunk_xy));
+        }
+    }
+
+    let tiles_bounds = grid.get_tiles_bounds();
+    for y in 0..tiles_bounds.y {
+        for x in 0..tiles_bounds.x {
+            let tile = grid.get_tile(&MapTileXY { x, y }).unwrap();
+            let decoded_tile = decoded_grid.get_tile(&MapTileXY { x, y }).unwrap();
+            assert_eq!(tile.visual_type, decoded_tile.visual_type);
+            assert_eq!(tile.walkability_blocked, decoded_tile.walkability_blocked);
+        }
+    }
+}
+
+#[test]
+fn decode_chunk_rejects_invalid_data() {
+    let grid = generate_example_map_grid();
+    let bytes = encode_chunk(&grid, &MapChunkXY { x: 1, y: 0 });
+    let mut decoded_grid = MapGrid::new(&grid.get_chunks_bounds());
+
+    let mut wrong_version = bytes.clone();
+    wrong_version[0] = CHUNK_ENCODING_VERSION + 1;
+    assert_eq!(
+        decode_chunk(&mut decoded_grid, &wrong_version),
+        Err(ChunkDecodeError::UnsupportedVersion(
+            CHUNK_ENCODING_VERSION + 1
+        ))
+    );
+    assert_eq!(
+        decode_chunk(&mut decoded_grid, &bytes[..bytes.len() - 1]),
+        Err(ChunkDecodeError::UnexpectedEnd)
+    );
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert_eq!(
+        decode_chunk(&mut decoded_grid, &trailing),
+        Err(ChunkDecodeError::TrailingBytes)
+    );
+
+    let mut small_grid = MapGrid::new(&MapChunkXY { x: 1, y: 1 });
+    assert_eq!(
+        decode_chunk(&mut small_grid, &bytes),
+        Err(ChunkDecodeError::ChunkOutOfBounds { x: 1, y: 0 })
+    );
+}
+
+#[test]
+fn read_varint_rejects_overflow() {
+    let mut bytes: Vec<u8> = Vec::new();
+    write_varint(&mut bytes, u32::MAX);
+    assert_eq!(bytes, [0xff, 0xff, 0xff, 0xff, 0x0f]);
+    assert_eq!(ByteReader::new(&bytes).read_varint(), Ok(u32::MAX));
+
+    // Bits past the 32nd in the 5th byte, or a 6th byte
+    assert_eq!(
+        ByteReader::new(&[0xff, 0xff, 0xff, 0xff, 0x1f]).read_varint(),
+        Err(ChunkDecodeError::InvalidVarint)
+    );
+    assert_eq!(
+        ByteReader::new(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x00]).read_varint(),
+        Err(ChunkDecodeError::InvalidVarint)
+    );
+}
+
+#[test]
+fn encode_uniform_chunk() {
+    let grid = MapGrid::new(&MapChunkXY { x: 1, y: 1 });
+    // Header, a single palette entry and a single run
+    let bytes = encode_chunk(&grid, &MapChunkXY { x: 0, y: 0 });
+    assert!(bytes.len() <= 16, "Got {} bytes", bytes.len());
+}
@@ -0,0 +1,174 @@
+//! Tile changes of a single `MapChunk` relative to the chunk `MapGenerator` produces, so only the
+//! changes need to be sent to clients, which regenerate the rest themselves.
+//!
+//! Layout, with integers little-endian:
+//! - `u8` encoding version, see `CHUNK_DELTA_ENCODING_VERSION`
+//! - `u32` chunk x and `u32` chunk y
+//! - LEB128 number of changed tiles
+//! - per changed tile, in scan order: a LEB128 count of unchanged tiles skipped since the previous
+//!   changed tile, followed by the `u8` tile state (see `chunk_codec`)
+
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use effigy_shared::game::map::{MapChunkXY, MapGrid, MapTileVisualType, SquareBounds};
+
+use super::chunk_codec::{
+    decode_tile_state, encode_tile_state, get_chunk_tile_states, get_chunk_tile_xy, write_varint,
+    ByteReader, ChunkDecodeError, CHUNK_TILES_COUNT,
+};
+
+/// Version written to the header of every encoded delta. Bump whenever the layout changes.
+pub const CHUNK_DELTA_ENCODING_VERSION: u8 = 1;
+
+/// New state of a tile that differs from the generated map
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileChange {
+    /// Scan-order index of the tile within its chunk
+    pub local_index: usize,
+    pub visual_type: MapTileVisualType,
+    pub walkability_blocked: SquareBounds,
+}
+
+/// Every tile of a chunk that differs from the generated map, in scan order
+#[derive(Debug, PartialEq)]
+pub struct ChunkDelta {
+    pub chunk_xy: MapChunkXY,
+    pub changes: Vec<TileChange>,
+}
+impl ChunkDelta {
+    /// Whether the chunk is unchanged from the generated map
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// Finds every tile of the chunk whose visual type or walkability in `live` differs from
+/// `baseline`, which should be the map freshly generated with the same seed and parameters
+pub fn diff_chunk(baseline: &MapGrid, live: &MapGrid, chunk_xy: &MapChunkXY) -> ChunkDelta {
+    assert_eq!(
+        baseline.get_chunks_bounds(),
+        live.get_chunks_bounds(),
+        "Expected the baseline and live maps to be the same size"
+    );
+
+    let baseline_states = get_chunk_tile_states(baseline, chunk_xy);
+    let live_states = get_chunk_tile_states(live, chunk_xy);
+    let changes = baseline_states
+        .iter()
+        .zip(&live_states)
+        .enumerate()
+        .filter(|(_, (baseline_state, live_state))| baseline_state != live_state)
+        .map(|(local_index, (_, &live_state))| {
+            let (visual_type, walkability_blocked) = decode_tile_state(live_state).unwrap();
+            TileChange {
+                local_index,
+                visual_type,
+                walkability_blocked,
+            }
+        })
+        .collect();
+    ChunkDelta {
+        chunk_xy: MapChunkXY {
+            x: chunk_xy.x,
+            y: chunk_xy.y,
+        },
+        changes,
+    }
+}
+
+pub fn encode_chunk_delta(delta: &ChunkDelta) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::with_capacity(10 + 2 * delta.changes.len());
+    bytes.push(CHUNK_DELTA_ENCODING_VERSION);
+    bytes.extend_from_slice(&(delta.chunk_xy.x as u32).to_le_bytes());
+    bytes.extend_from_slice(&(delta.chunk_xy.y as u32).to_le_bytes());
+    write_varint(&mut bytes, delta.changes.len() as u32);
+    let mut next_index: usize = 0;
+    for change in &delta.changes {
+        assert!(
+            change.local_index >= next_index && change.local_index < CHUNK_TILES_COUNT,
+            "Expected tile changes in scan order within the chunk, found tile {}",
+            change.local_index
+        );
+        write_varint(&mut bytes, (change.local_index - next_index) as u32);
+        bytes.push(encode_tile_state(
+            &change.visual_type,
+            &change.walkability_blocked,
+        ));
+        next_index = change.local_index + 1;
+    }
+    bytes
+}
+
+pub fn decode_chunk_delta(bytes: &[u8]) -> Result<ChunkDelta, ChunkDecodeError> {
+    let mut reader = ByteReader::new(bytes);
+    let version = reader.read_u8()?;
+    if version != CHUNK_DELTA_ENCODING_VERSION {
+        return Err(ChunkDecodeError::UnsupportedVersion(version));
+    }
+    let chunk_x = reader.read_u32()?;
+    let chunk_y = reader.read_u32()?;
+
+    let changes_count = reader.read_varint()? as usize;
+    if changes_count > CHUNK_TILES_COUNT {
+        return Err(ChunkDecodeError::InvalidTilesCount(changes_count));
+    }
+    let mut changes: Vec<TileChange> = Vec::with_capacity(changes_count);
+    let mut next_index: usize = 0;
+    for _ in 0..changes_count {
+        let gap = reader.read_varint()? as usize;
+        let local_index = next_index
+            .checked_add(gap)
+            .filter(|index| *index < CHUNK_TILES_COUNT)
+            .ok_or(ChunkDecodeError::InvalidTileIndex(
+                next_index.saturating_add(gap),
+            ))?;
+        let (visual_type, walkability_blocked) = decode_tile_state(reader.read_u8()?)?;
+        changes.push(TileChange {
+            local_index,
+            visual_type,
+            walkability_blocked,
+        });
+        next_index = local_index + 1;
+    }
+    reader.finish()?;
+
+    Ok(ChunkDelta {
+        chunk_xy: MapChunkXY {
+            x: chunk_x as _,
+            y: chunk_y as _,
+        },
+        changes,
+    })
+}
+
+/// Writes the changed tiles into the map, which should be the freshly generated baseline the delta
+/// was taken against
+pub fn apply_chunk_delta(
+    map_grid: &mut MapGrid,
+    delta: &ChunkDelta,
+) -> Result<(), ChunkDecodeError> {
+    let chunks_bounds = map_grid.get_chunks_bounds();
+    if delta.chunk_xy.x >= chunks_bounds.x || delta.chunk_xy.y >= chunks_bounds.y {
+        return Err(ChunkDecodeError::ChunkOutOfBounds {
+            x: delta.chunk_xy.x as u32,
+            y: delta.chunk_xy.y as u32,
+        });
+    }
+    if let Some(change) = delta
+        .changes
+        .iter()
+        .find(|change| change.local_index >= CHUNK_TILES_COUNT)
+    {
+        return Err(ChunkDecodeError::InvalidTileIndex(change.local_index));
+    }
+
+    for change in &delta.changes {
+        map_grid
+            .get_tile_mut(&get_chunk_tile_xy(&delta.chunk_xy, change.local_index))
+            .unwrap()
+            .set_visual_type(&change.visual_type)
+            .set_walkability_blocked(&change.walkability_blocked);
+    }
+    Ok(())
+}
@@ -0,0 +1,131 @@
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use effigy_shared::game::map::{
+    MapChunkXY, MapGrid, MapTileVisualType, MapTileXY, SquareBounds, MAP_CHUNK_TILES_LENGTH,
+};
+
+use super::chunk_codec::{get_chunk_tile_xy, ChunkDecodeError};
+use super::chunk_delta::{apply_chunk_delta, decode_chunk_delta, diff_chunk, encode_chunk_delta};
+use super::map_gen::MapGenerator;
+
+fn generate_example_map_grid() -> MapGrid {
+    MapGenerator::new(453537, &MapChunkXY { x: 2, y: 2 })
+        .with_terrain(0.2, 0.5)
+        .with_buildings(0.1, 0.25)
+        .with_water(0.2, 0.5)
+        .with_trees(0.2, 0.5)
+        .generate()
+}
+
+#[test]
+fn chunk_delta_round_trip() {
+    let mut live = generate_example_map_grid();
+    // Chop a tree and build a wall in the second chunk
+    let length = MAP_CHUNK_TILES_LENGTH;
+    let changed_tiles = [
+        (
+            MapTileXY {
+                x: length + 8,
+                y: 3,
+            },
+            MapTileVisualType::Land,
+        ),
+        (
+            MapTileXY {
+                x: length + 9,
+                y: 3,
+            },
+            MapTileVisualType::Terrain,
+        ),
+        (
+            MapTileXY {
+                x: 2 * length - 1,
+                y: length - 1,
+            },
+            MapTileVisualType::Building,
+        ),
+    ];
+    for (tile_xy, visual_type) in &changed_tiles {
+        live.get_tile_mut(tile_xy)
+            .unwrap()
+            .set_visual_type(visual_type)
+            .set_walkability_blocked(&SquareBounds::ALL);
+    }
+
+    let baseline = generate_example_map_grid();
+    let chunk_xy = MapChunkXY { x: 1, y: 0 };
+    let delta = diff_chunk(&baseline, &live, &chunk_xy);
+    // Exactly the tiles which differ, as some edits may match the generated tile
+    let delta_tiles: Vec<MapTileXY> = delta
+        .changes
+        .iter()
+        .map(|change| get_chunk_tile_xy(&chunk_xy, change.local_index))
+        .collect();
+    let differing_tiles: Vec<MapTileXY> = (0..length)
+        .flat_map(|y| (length..(2 * length)).map(move |x| MapTileXY { x, y }))
+        .filter(|tile_xy| {
+            let (baseline_tile, live_tile) = (
+                baseline.get_tile(tile_xy).unwrap(),
+                live.get_tile(tile_xy).unwrap(),
+            );
+            baseline_tile.visual_type != live_tile.visual_type
+                || baseline_tile.walkability_blocked != live_tile.walkability_blocked
+        })
+        .collect();
+    assert!(!differing_tiles.is_empty());
+    assert_eq!(delta_tiles, differing_tiles);
+    assert!(diff_chunk(&baseline, &live, &MapChunkXY { x: 0, y: 1 }).is_empty());
+
+    let bytes = encode_chunk_delta(&delta);
+    assert!(bytes.len() <= 10 + 3 * delta.changes.len());
+    let decoded_delta = decode_chunk_delta(&bytes).unwrap();
+    assert_eq!(decoded_delta, delta);
+
+    let mut client = generate_example_map_grid();
+    apply_chunk_delta(&mut client, &decoded_delta).unwrap();
+    for (tile_xy, visual_type) in &changed_tiles {
+        let tile = client.get_tile(tile_xy).unwrap();
+        assert_eq!(&tile.visual_type, visual_type);
+        assert_eq!(tile.walkability_blocked, SquareBounds::ALL);
+    }
+    assert!(diff_chunk(&client, &live, &chunk_xy).is_empty());
+}
+
+#[test]
+fn decode_chunk_delta_rejects_invalid_data() {
+    let baseline = generate_example_map_grid();
+    let delta = diff_chunk(&baseline, &baseline, &MapChunkXY { x: 1, y: 1 });
+    let bytes = encode_chunk_delta(&delta);
+    assert_eq!(decode_chunk_delta(&bytes).unwrap(), delta);
+
+    assert_eq!(
+        decode_chunk_delta(&bytes[..bytes.len() - 1]),
+        Err(ChunkDecodeError::UnexpectedEnd)
+    );
+    // One change skipping past the end of the chunk
+    let mut past_end = bytes.clone();
+    *past_end.last_mut().unwrap() = 1;
+    // Skipping 32767 tiles
+    past_end.extend_from_slice(&[0xff, 0xff, 0x01, 0]);
+    assert_eq!(
+        decode_chunk_delta(&past_end),
+        Err(ChunkDecodeError::InvalidTileIndex(32767))
+    );
+    // A second change skipping as far as a varint can, which must not wrap around to a valid index
+    let mut huge_gap = bytes.clone();
+    *huge_gap.last_mut().unwrap() = 2;
+    huge_gap.extend_from_slice(&[0, 0, 0xff, 0xff, 0xff, 0xff, 0x0f, 0]);
+    assert_eq!(
+        decode_chunk_delta(&huge_gap),
+        Err(ChunkDecodeError::InvalidTileIndex(
+            1usize.saturating_add(0xffff_ffff)
+        ))
+    );
+
+    let mut small_grid = MapGrid::new(&MapChunkXY { x: 1, y: 1 });
+    assert_eq!(
+        apply_chunk_delta(&mut small_grid, &delta),
+        Err(ChunkDecodeError::ChunkOutOfBounds { x: 1, y: 1 })
+    );
+}
@@ -0,0 +1,224 @@
+//! Persists the edits made to a generated map between server restarts, without storing the
+//! whole map: each modified chunk is saved as its `ChunkDelta` from the generated map, and loading
+//! regenerates the map and reapplies the deltas.
+//!
+//! The store is a directory with one file per modified chunk, named by chunk coordinate. Each file
+//! starts with `CHUNK_STORE_MAGIC` and the `u64` little-endian fingerprint of the generator the
+//! delta was taken against (see `MapGenerator::get_fingerprint`), followed by the encoded delta
+//! (see `chunk_delta`).
+
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use effigy_shared::game::map::{MapChunkXY, MapGrid};
+
+use super::chunk_codec::{ByteReader, ChunkDecodeError};
+use super::chunk_delta::{
+    apply_chunk_delta, decode_chunk_delta, diff_chunk, encode_chunk_delta, ChunkDelta,
+};
+use super::map_gen::MapGenerator;
+
+/// Bytes every chunk file starts with
+pub const CHUNK_STORE_MAGIC: [u8; 4] = *b"EFCD";
+
+#[derive(Debug)]
+pub enum ChunkStoreError {
+    Io(io::Error),
+    Decode(ChunkDecodeError),
+    /// The chunk was saved from a different generator, or an older `MAP_GENERATOR_VERSION`, so its
+    /// delta no longer applies to the generated map
+    FingerprintMismatch {
+        expected: u64,
+        found: u64,
+    },
+    /// The file of a chunk held a delta for another chunk
+    ChunkMismatch {
+        expected: MapChunkXY,
+        found: MapChunkXY,
+    },
+}
+impl fmt::Display for ChunkStoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkStoreError::Io(error) => write!(f, "chunk store I/O error: {}", error),
+            ChunkStoreError::Decode(error) => write!(f, "invalid chunk file: {}", error),
+            ChunkStoreError::FingerprintMismatch { expected, found } => write!(
+                f,
+                "chunk was saved by generator {:016x}, expected {:016x}",
+                found, expected
+            ),
+            ChunkStoreError::ChunkMismatch { expected, found } => write!(
+                f,
+                "chunk file of ({}, {}) holds chunk ({}, {})",
+                expected.x, expected.y, found.x, found.y
+            ),
+        }
+    }
+}
+impl std::error::Error for ChunkStoreError {}
+impl From<io::Error> for ChunkStoreError {
+    fn from(error: io::Error) -> Self {
+        ChunkStoreError::Io(error)
+    }
+}
+impl From<ChunkDecodeError> for ChunkStoreError {
+    fn from(error: ChunkDecodeError) -> Self {
+        ChunkStoreError::Decode(error)
+    }
+}
+
+/// Directory of chunk deltas, all taken against the map of one generator
+pub struct ChunkStore {
+    directory: PathBuf,
+    fingerprint: u64,
+}
+impl ChunkStore {
+    /// Opens the store in `directory`, creating it if needed, for maps produced by generators with
+    /// the given fingerprint. See: `MapGenerator::get_fingerprint`
+    pub fn open(directory: impl AsRef<Path>, fingerprint: u64) -> io::Result<Self> {
+        fs::create_dir_all(directory.as_ref())?;
+        Ok(ChunkStore {
+            directory: directory.as_ref().to_path_buf(),
+            fingerprint,
+        })
+    }
+
+    pub fn get_fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+
+    /// Saves the delta of a chunk, replacing any delta saved before. An empty delta removes the
+    /// chunk from the store, as it's the same as the generated chunk.
+    pub fn save_chunk(&self, delta: &ChunkDelta) -> Result<(), ChunkStoreError> {
+        let path = self.get_chunk_path(&delta.chunk_xy);
+        if delta.is_empty() {
+            return match fs::remove_file(&path) {
+                Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error.into()),
+                _ => Ok(()),
+            };
+        }
+
+        let mut bytes: Vec<u8> = Vec::new();
+        bytes.extend_from_slice(&CHUNK_STORE_MAGIC);
+        bytes.extend_from_slice(&self.fingerprint.to_le_bytes());
+        bytes.extend_from_slice(&encode_chunk_delta(delta));
+        // Write the whole file before replacing the old one, so a crash can't leave half a chunk
+        let temp_path = path.with_extension("tmp");
+        fs::write(&temp_path, &bytes)?;
+        fs::rename(&temp_path, &path)?;
+        Ok(())
+    }
+
+    /// Saves the delta of every chunk of `live` from `baseline`, the map freshly generated with the
+    /// store's generator, returning how many chunks differ
+    pub fn save_map(&self, baseline: &MapGrid, live: &MapGrid) -> Result<usize, ChunkStoreError> {
+        let chunks_bounds = live.get_chunks_bounds();
+        assert!(
+            baseline.get_chunks_bounds() == chunks_bounds,
+            "Expected the baseline and live maps to have the same bounds"
+        );
+        let mut changed_count: usize = 0;
+        for chunk_y in 0..chunks_bounds.y {
+            for chunk_x in 0..chunks_bounds.x {
+                let delta = diff_chunk(
+                    baseline,
+                    live,
+                    &MapChunkXY {
+                        x: chunk_x,
+                        y: chunk_y,
+                    },
+                );
+                if !delta.is_empty() {
+                    changed_count += 1;
+                }
+                self.save_chunk(&delta)?;
+            }
+        }
+        Ok(changed_count)
+    }
+
+    /// Loads the delta saved for a chunk, or `None` if the chunk is unchanged
+    pub fn load_chunk(&self, chunk_xy: &MapChunkXY) -> Result<Option<ChunkDelta>, ChunkStoreError> {
+        let bytes = match fs::read(self.get_chunk_path(chunk_xy)) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+
+        let mut reader = ByteReader::new(&bytes);
+        if reader.read_bytes(CHUNK_STORE_MAGIC.len())? != CHUNK_STORE_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a chunk file").into());
+        }
+        let found = u64::from_le_bytes(reader.read_bytes(8)?.try_into().unwrap());
+        if found != self.fingerprint {
+            return Err(ChunkStoreError::FingerprintMismatch {
+                expected: self.fingerprint,
+                found,
+            });
+        }
+        let delta = decode_chunk_delta(&bytes[CHUNK_STORE_MAGIC.len() + 8..])?;
+        if delta.chunk_xy != *chunk_xy {
+            return Err(ChunkStoreError::ChunkMismatch {
+                expected: MapChunkXY {
+                    x: chunk_xy.x,
+                    y: chunk_xy.y,
+                },
+                found: delta.chunk_xy,
+            });
+        }
+        Ok(Some(delta))
+    }
+
+    /// Reapplies the delta saved for a chunk, if any, to a map holding the generated chunk, e.g. to
+    /// load chunks as players reach them. Returns whether the chunk had a delta.
+    pub fn load_chunk_into(
+        &self,
+        map_grid: &mut MapGrid,
+        chunk_xy: &MapChunkXY,
+    ) -> Result<bool, ChunkStoreError> {
+        match self.load_chunk(chunk_xy)? {
+            Some(delta) => {
+                apply_chunk_delta(map_grid, &delta)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Regenerates the whole map and reapplies every delta saved for it. Fails without generating
+    /// if the generator's fingerprint doesn't match the store's.
+    ///
+    /// Generation always covers the whole map, so a server streaming chunks in should generate the
+    /// map once and use `load_chunk_into` per chunk instead.
+    pub fn load_map(&self, generator: MapGenerator) -> Result<MapGrid, ChunkStoreError> {
+        if generator.get_fingerprint() != self.fingerprint {
+            return Err(ChunkStoreError::FingerprintMismatch {
+                expected: self.fingerprint,
+                found: generator.get_fingerprint(),
+            });
+        }
+
+        let mut map_grid = generator.generate();
+        let chunks_bounds = map_grid.get_chunks_bounds();
+        for chunk_y in 0..chunks_bounds.y {
+            for chunk_x in 0..chunks_bounds.x {
+                let chunk_xy = MapChunkXY {
+                    x: chunk_x,
+                    y: chunk_y,
+                };
+                self.load_chunk_into(&mut map_grid, &chunk_xy)?;
+            }
+        }
+        Ok(map_grid)
+    }
+
+    fn get_chunk_path(&self, chunk_xy: &MapChunkXY) -> PathBuf {
+        self.directory
+            .join(format!("chunk_{}_{}.bin", chunk_xy.x, chunk_xy.y))
+    }
+}
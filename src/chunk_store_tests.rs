@@ -0,0 +1,147 @@
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use std::path::PathBuf;
+
+use noise::core::worley::ReturnType;
+
+use effigy_shared::game::map::{
+    MapChunkXY, MapTileVisualType, MapTileXY, SquareBounds, MAP_CHUNK_TILES_LENGTH,
+};
+
+use super::chunk_store::{ChunkStore, ChunkStoreError};
+use super::map_gen::MapGenerator;
+use super::noise_gen::{BuildingDistanceFunction, BuildingNoiseStyle};
+
+fn get_example_generator(seed: u32) -> MapGenerator {
+    MapGenerator::new(seed, &MapChunkXY { x: 2, y: 2 })
+        .with_terrain(0.2, 0.5)
+        .with_water(0.2, 0.5)
+        .with_trees(0.2, 0.5)
+}
+
+fn get_temp_directory(name: &str) -> PathBuf {
+    let directory =
+        std::env::temp_dir().join(format!("effigy_gen_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    directory
+}
+
+#[test]
+fn chunk_store_round_trip() {
+    let directory = get_temp_directory("chunk_store_round_trip");
+    let store =
+        ChunkStore::open(&directory, get_example_generator(453537).get_fingerprint()).unwrap();
+
+    let baseline = get_example_generator(453537).generate();
+    let mut live = get_example_generator(453537).generate();
+    for x in 0..4 {
+        live.get_tile_mut(&MapTileXY { x, y: 40 })
+            .unwrap()
+            .set_visual_type(&MapTileVisualType::Building)
+            .set_walkability_blocked(&SquareBounds::ALL);
+    }
+    assert_eq!(store.save_map(&baseline, &live).unwrap(), 1);
+    assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 1);
+
+    let loaded = store.load_map(get_example_generator(453537)).unwrap();
+    let tiles_bounds = live.get_tiles_bounds();
+    for y in 0..tiles_bounds.y {
+        for x in 0..tiles_bounds.x {
+            let tile = live.get_tile(&MapTileXY { x, y }).unwrap();
+            let loaded_tile = loaded.get_tile(&MapTileXY { x, y }).unwrap();
+            assert_eq!(tile.visual_type, loaded_tile.visual_type);
+            assert_eq!(tile.walkability_blocked, loaded_tile.walkability_blocked);
+        }
+    }
+
+    // Loading chunk by chunk only changes the edited chunk
+    let mut streamed = get_example_generator(453537).generate();
+    let edited_chunk_xy = MapChunkXY {
+        x: 0,
+        y: 40 / MAP_CHUNK_TILES_LENGTH,
+    };
+    let chunks_bounds = streamed.get_chunks_bounds();
+    for y in 0..chunks_bounds.y {
+        for x in 0..chunks_bounds.x {
+            let chunk_xy = MapChunkXY { x, y };
+            assert_eq!(
+                store.load_chunk_into(&mut streamed, &chunk_xy).unwrap(),
+                chunk_xy == edited_chunk_xy
+            );
+        }
+    }
+    assert_eq!(
+        streamed
+            .get_tile(&MapTileXY { x: 0, y: 40 })
+            .unwrap()
+            .visual_type,
+        MapTileVisualType::Building
+    );
+
+    // Reverting the edits removes the chunk from the store
+    assert_eq!(store.save_map(&baseline, &baseline).unwrap(), 0);
+    assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 0);
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn chunk_store_detects_other_generator() {
+    let directory = get_temp_directory("chunk_store_detects_other_generator");
+    let generator = get_example_generator(453537);
+    assert_ne!(
+        generator.get_fingerprint(),
+        get_example_generator(453538).get_fingerprint()
+    );
+    assert_ne!(
+        generator.get_fingerprint(),
+        get_example_generator(453537)
+            .with_trees(0.1, 0.5)
+            .get_fingerprint()
+    );
+    // Custom distance functions are only told apart by id
+    let get_custom_fingerprint = |id: &'static str, distance_function| {
+        let style = BuildingNoiseStyle {
+            distance_function: BuildingDistanceFunction::Custom {
+                id,
+                distance_function,
+            },
+            return_type: ReturnType::Value,
+        };
+        get_example_generator(453537)
+            .with_styled_buildings(0.1, 0.25, &style)
+            .get_fingerprint()
+    };
+    let fn_a: fn(&[f64], &[f64]) -> f64 = |a, b| (a[0] - b[0]).abs();
+    let fn_b: fn(&[f64], &[f64]) -> f64 = |a, b| (a[1] - b[1]).abs();
+    assert_ne!(
+        get_custom_fingerprint("horizontal", fn_a),
+        get_custom_fingerprint("vertical", fn_b)
+    );
+    assert_eq!(
+        get_custom_fingerprint("horizontal", fn_a),
+        get_custom_fingerprint("horizontal", fn_a)
+    );
+
+    let store = ChunkStore::open(&directory, generator.get_fingerprint()).unwrap();
+    let baseline = generator.generate();
+    let mut live = get_example_generator(453537).generate();
+    live.get_tile_mut(&MapTileXY { x: 0, y: 0 })
+        .unwrap()
+        .set_visual_type(&MapTileVisualType::Water);
+    store.save_map(&baseline, &live).unwrap();
+
+    assert!(matches!(
+        store.load_map(get_example_generator(453538)),
+        Err(ChunkStoreError::FingerprintMismatch { .. })
+    ));
+    let other_store =
+        ChunkStore::open(&directory, get_example_generator(453538).get_fingerprint()).unwrap();
+    assert!(matches!(
+        other_store.load_chunk(&MapChunkXY { x: 0, y: 0 }),
+        Err(ChunkStoreError::FingerprintMismatch { .. })
+    ));
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
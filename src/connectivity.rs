@@ -0,0 +1,319 @@
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use std::collections::VecDeque;
+
+use effigy_shared::game::map::{MapGrid, MapTileVisualType, SquareBounds};
+
+use super::grid_index::{
+    get_neighbor_indices, get_tile_at_index, get_tile_at_index_mut, NO_BLOCKED_EDGES, VISUAL_TYPES,
+};
+use super::map_gen::simplify_map_walkability_blockage;
+use super::regions::{label_map_regions, MapRegionId, MapRegions, RegionConnectivity};
+use super::walkability::WalkabilityRules;
+
+/// How `MapGenerator::generate` deals with walkable land that players can't reach from the rest
+/// of the map
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectivityMode {
+    /// Carve terrain or tree tiles into land so every walkable region joins the largest one. Each
+    /// region in turn gets the passage carving the fewest tiles to anything already joined, which
+    /// isn't always the fewest tiles overall. Regions sealed off entirely by water or buildings are
+    /// left as-is.
+    CarvePassages,
+    /// Fill every walkable region of at most `max_area` tiles, other than the largest, with the
+    /// blocking tile type that surrounds it the most
+    FillPockets { max_area: usize },
+}
+
+const fn is_carvable(visual_type: &MapTileVisualType) -> bool {
+    matches!(
+        visual_type,
+        MapTileVisualType::Terrain | MapTileVisualType::LandTree
+    )
+}
+
+/// Whether units can step between tiles of the two types in both directions, which is what joins
+/// tiles into a walkable region
+fn is_joined(rules: &WalkabilityRules, a: &MapTileVisualType, b: &MapTileVisualType) -> bool {
+    rules.is_passable(a, b) && rules.is_passable(b, a)
+}
+
+/// Land, and every tile type joined to land by the rules, directly or through other such types
+fn get_walkable_types(rules: &WalkabilityRules) -> Vec<MapTileVisualType> {
+    let mut walkable_types = vec![MapTileVisualType::Land];
+    let mut is_growing = true;
+    while is_growing {
+        is_growing = false;
+        for visual_type in VISUAL_TYPES.iter() {
+            if !walkable_types.contains(visual_type)
+                && walkable_types
+                    .iter()
+                    .any(|walkable_type| is_joined(rules, walkable_type, visual_type))
+            {
+                walkable_types.push(visual_type.clone());
+                is_growing = true;
+            }
+        }
+    }
+    walkable_types
+}
+
+/// Connects or removes unreachable walkable regions of the map, as configured by `mode`.
+///
+/// Walkable tiles are those of the types `get_walkable_types` finds from the rules, joined into
+/// regions across edges the rules open in both directions. This runs before walkability blockage
+/// is simplified for the final map, so it simplifies it for the layers as placed first.
+pub(crate) fn enforce_walkable_connectivity(
+    map_grid: &mut MapGrid,
+    mode: &ConnectivityMode,
+    rules: &WalkabilityRules,
+) {
+    info!("Enforcing walkable connectivity: mode={:?} ...", mode);
+    let func_start_time = std::time::Instant::now();
+
+    simplify_map_walkability_blockage(map_grid, rules, false);
+    let regions = label_map_regions(map_grid, RegionConnectivity::Walkability);
+    let walkable_types = get_walkable_types(rules);
+    // Regions only join tiles of joined types, so a region is walkable if its first tile is
+    let land_region_ids: Vec<MapRegionId> = regions
+        .get_regions()
+        .iter()
+        .filter(|region| walkable_types.contains(&region.visual_type))
+        .map(|region| region.id)
+        .collect();
+    // Largest walkable region, preferring the first labeled on ties
+    let largest_region_id = match land_region_ids
+        .iter()
+        .copied()
+        .reduce(|largest, region_id| {
+            if regions.get_region(region_id).unwrap().area
+                > regions.get_region(largest).unwrap().area
+            {
+                region_id
+            } else {
+                largest
+            }
+        }) {
+        Some(region_id) => region_id,
+        None => {
+            info!("... no walkable tiles, nothing to connect");
+            return;
+        }
+    };
+    info!("... found {} walkable regions ...", land_region_ids.len());
+
+    match mode {
+        ConnectivityMode::CarvePassages => carve_passages(
+            map_grid,
+            &regions,
+            &land_region_ids,
+            largest_region_id,
+            rules,
+            &walkable_types,
+        ),
+        ConnectivityMode::FillPockets { max_area } => fill_pockets(
+            map_grid,
+            &regions,
+            &land_region_ids,
+            largest_region_id,
+            *max_area,
+        ),
+    }
+
+    info!(
+        "... done enforcing walkable connectivity! Completed in {:.3} seconds",
+        func_start_time.elapsed().as_secs_f32()
+    );
+}
+
+fn carve_passages(
+    map_grid: &mut MapGrid,
+    regions: &MapRegions,
+    land_region_ids: &[MapRegionId],
+    largest_region_id: MapRegionId,
+    rules: &WalkabilityRules,
+    walkable_types: &[MapTileVisualType],
+) {
+    let width = map_grid.get_tiles_bounds().x as usize;
+    let height = map_grid.get_tiles_bounds().y as usize;
+
+    let mut connected: Vec<bool> = regions
+        .get_tile_region_ids()
+        .iter()
+        .map(|&region_id| region_id == largest_region_id)
+        .collect();
+
+    // Search state shared by every region, only resetting the tiles each search reached
+    let mut costs = vec![usize::MAX; width * height];
+    let mut previous = vec![usize::MAX; width * height];
+    let mut reached_indices: Vec<usize> = Vec::new();
+    let mut queue: VecDeque<usize> = VecDeque::new();
+
+    let mut carved_count: usize = 0;
+    for &region_id in land_region_ids {
+        let region_indices = regions.get_region_tile_indices(region_id);
+        // Skip regions already joined by an earlier passage
+        if connected[region_indices[0]] {
+            continue;
+        }
+
+        // 0-1 breadth-first search from the region: walking into a joined walkable tile is free,
+        // carving a tile into land costs 1
+        for &index in reached_indices.iter() {
+            costs[index] = usize::MAX;
+            previous[index] = usize::MAX;
+        }
+        reached_indices.clear();
+        queue.clear();
+        for &index in region_indices {
+            costs[index] = 0;
+            reached_indices.push(index);
+            queue.push_back(index);
+        }
+        let mut reached_index: Option<usize> = None;
+        while let Some(index) = queue.pop_front() {
+            if connected[index] {
+                reached_index = Some(index);
+                break;
+            }
+            // Tiles reached by carving will be land
+            let is_carved = previous[index] != usize::MAX && costs[index] > costs[previous[index]];
+            let tile_type = if is_carved {
+                &MapTileVisualType::Land
+            } else {
+                &get_tile_at_index(map_grid, width, index).visual_type
+            };
+            for neighbor_index in get_neighbor_indices(width, height, index) {
+                let neighbor_type = &get_tile_at_index(map_grid, width, neighbor_index).visual_type;
+                let step_cost = if walkable_types.contains(neighbor_type)
+                    && is_joined(rules, tile_type, neighbor_type)
+                {
+                    0
+                } else if is_carvable(neighbor_type)
+                    && is_joined(rules, tile_type, &MapTileVisualType::Land)
+                {
+                    1
+                } else {
+                    continue;
+                };
+                if costs[index] + step_cost < costs[neighbor_index] {
+                    if costs[neighbor_index] == usize::MAX {
+                        reached_indices.push(neighbor_index);
+                    }
+                    costs[neighbor_index] = costs[index] + step_cost;
+                    previous[neighbor_index] = index;
+                    if step_cost == 0 {
+                        queue.push_front(neighbor_index);
+                    } else {
+                        queue.push_back(neighbor_index);
+                    }
+                }
+            }
+        }
+
+        let mut index = match reached_index {
+            Some(index) => index,
+            None => {
+                debug!(
+                    "Walkable region {} is sealed off by uncarvable tiles, leaving as-is",
+                    region_id
+                );
+                continue;
+            }
+        };
+        while previous[index] != usize::MAX {
+            let previous_index = previous[index];
+            if costs[index] > costs[previous_index] {
+                get_tile_at_index_mut(map_grid, width, index)
+                    .set_visual_type(&MapTileVisualType::Land)
+                    .set_walkability_blocked(&NO_BLOCKED_EDGES);
+                carved_count += 1;
+            }
+            index = previous_index;
+        }
+
+        // Everything walkable from the region (including the new passage) is now joined
+        queue.clear();
+        queue.extend(region_indices.iter().copied());
+        while let Some(index) = queue.pop_front() {
+            if connected[index] {
+                continue;
+            }
+            connected[index] = true;
+            let tile_type = &get_tile_at_index(map_grid, width, index).visual_type;
+            for neighbor_index in get_neighbor_indices(width, height, index) {
+                let neighbor_type = &get_tile_at_index(map_grid, width, neighbor_index).visual_type;
+                if !connected[neighbor_index]
+                    && walkable_types.contains(neighbor_type)
+                    && is_joined(rules, tile_type, neighbor_type)
+                {
+                    queue.push_back(neighbor_index);
+                }
+            }
+        }
+    }
+
+    info!("... carved {} tiles into passages ...", carved_count);
+}
+
+fn fill_pockets(
+    map_grid: &mut MapGrid,
+    regions: &MapRegions,
+    land_region_ids: &[MapRegionId],
+    largest_region_id: MapRegionId,
+    max_area: usize,
+) {
+    let width = map_grid.get_tiles_bounds().x as usize;
+    let height = map_grid.get_tiles_bounds().y as usize;
+
+    let mut filled_count: usize = 0;
+    for &region_id in land_region_ids {
+        if region_id == largest_region_id || regions.get_region(region_id).unwrap().area > max_area
+        {
+            continue;
+        }
+        let region_indices = regions.get_region_tile_indices(region_id);
+
+        // Tally the blocking tiles bordering the pocket, in `MapTileVisualType` declaration order
+        let mut border_counts: [(MapTileVisualType, usize); 4] = [
+            (MapTileVisualType::LandTree, 0),
+            (MapTileVisualType::Water, 0),
+            (MapTileVisualType::Terrain, 0),
+            (MapTileVisualType::Building, 0),
+        ];
+        for &index in region_indices {
+            for neighbor_index in get_neighbor_indices(width, height, index) {
+                if regions.get_tile_region_ids()[neighbor_index] == region_id {
+                    continue;
+                }
+                let neighbor_type = &get_tile_at_index(map_grid, width, neighbor_index).visual_type;
+                if let Some(border_count) = border_counts
+                    .iter_mut()
+                    .find(|(visual_type, _)| visual_type == neighbor_type)
+                {
+                    border_count.1 += 1;
+                }
+            }
+        }
+        // Pockets enclosed only by the map edge default to terrain
+        let fill_type = border_counts
+            .iter()
+            .filter(|(_, count)| *count > 0)
+            .reduce(|most, current| if current.1 > most.1 { current } else { most })
+            .map(|(visual_type, _)| visual_type.clone())
+            .unwrap_or(MapTileVisualType::Terrain);
+
+        for &index in region_indices {
+            get_tile_at_index_mut(map_grid, width, index)
+                .set_visual_type(&fill_type)
+                .set_walkability_blocked(&SquareBounds::ALL);
+        }
+        filled_count += region_indices.len();
+    }
+
+    info!(
+        "... filled {} tiles of unreachable pockets ...",
+        filled_count
+    );
+}
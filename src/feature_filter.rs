@@ -0,0 +1,152 @@
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use effigy_shared::game::map::{MapGrid, MapTileVisualType, SquareBounds};
+
+use super::grid_index::{
+    get_neighbor_indices, get_tile_at_index, get_tile_at_index_mut, NO_BLOCKED_EDGES,
+};
+use super::regions::{label_map_regions, RegionConnectivity};
+
+/// Removes connected features of one tile type that are smaller than `min_area` tiles.
+/// See: `MapGenerator::with_min_feature_size`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeatureSizeRule {
+    pub visual_type: MapTileVisualType,
+    pub min_area: usize,
+    /// Tile type that replaces removed features, or `None` to use the tile type that borders each
+    /// feature the most, e.g. to fill holes with whatever surrounds them
+    pub replacement: Option<MapTileVisualType>,
+}
+
+/// What a `FeatureSizeRule` found and removed when generating the map
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeatureSizeStats {
+    pub visual_type: MapTileVisualType,
+    pub min_area: usize,
+    /// Number of features of the tile type before filtering
+    pub regions_count: usize,
+    pub removed_regions_count: usize,
+    pub removed_tiles_count: usize,
+    /// Area of the smallest feature left on the map, or `None` if none are left
+    pub smallest_kept_area: Option<usize>,
+}
+
+/// Replaces every connected region of the rule's tile type with fewer than `min_area` tiles.
+///
+/// Regions are found with `RegionConnectivity::VisualType` and replaced in scan order, so a
+/// replacement picked from the bordering tiles sees the features replaced before it. This runs
+/// before walkability blockage is simplified, where a tile is walkable if it's land.
+pub(crate) fn filter_small_features(
+    map_grid: &mut MapGrid,
+    rule: &FeatureSizeRule,
+) -> FeatureSizeStats {
+    info!("Filtering small features: {:?} ...", rule);
+    let func_start_time = std::time::Instant::now();
+
+    let width = map_grid.get_tiles_bounds().x as usize;
+    let height = map_grid.get_tiles_bounds().y as usize;
+
+    let regions = label_map_regions(map_grid, RegionConnectivity::VisualType);
+    let mut stats = FeatureSizeStats {
+        visual_type: rule.visual_type.clone(),
+        min_area: rule.min_area,
+        regions_count: 0,
+        removed_regions_count: 0,
+        removed_tiles_count: 0,
+        smallest_kept_area: None,
+    };
+    for region in regions.get_regions() {
+        if region.visual_type != rule.visual_type {
+            continue;
+        }
+        stats.regions_count += 1;
+        if region.area >= rule.min_area {
+            record_kept_area(&mut stats, region.area);
+            continue;
+        }
+
+        let region_indices = regions.get_region_tile_indices(region.id);
+        let replacement = match rule
+            .replacement
+            .clone()
+            .or_else(|| get_most_common_border_type(map_grid, width, height, region_indices))
+        {
+            Some(replacement) => replacement,
+            None => {
+                debug!(
+                    "Feature {} has no bordering tiles to replace it with, leaving as-is",
+                    region.id
+                );
+                record_kept_area(&mut stats, region.area);
+                continue;
+            }
+        };
+        let walkability_blocked = if replacement == MapTileVisualType::Land {
+            NO_BLOCKED_EDGES
+        } else {
+            SquareBounds::ALL
+        };
+        for &index in region_indices {
+            get_tile_at_index_mut(map_grid, width, index)
+                .set_visual_type(&replacement)
+                .set_walkability_blocked(&walkability_blocked);
+        }
+        stats.removed_regions_count += 1;
+        stats.removed_tiles_count += region_indices.len();
+    }
+
+    info!(
+        "... removed {} of {} features ({} tiles), smallest remaining is {:?} tiles ...",
+        stats.removed_regions_count,
+        stats.regions_count,
+        stats.removed_tiles_count,
+        stats.smallest_kept_area
+    );
+    info!(
+        "... done filtering small features! Completed in {:.3} seconds",
+        func_start_time.elapsed().as_secs_f32()
+    );
+    stats
+}
+
+fn record_kept_area(stats: &mut FeatureSizeStats, area: usize) {
+    stats.smallest_kept_area = Some(stats.smallest_kept_area.map_or(area, |kept| kept.min(area)));
+}
+
+/// Tile type bordering the region the most, preferring `MapTileVisualType` declaration order on
+/// ties, or `None` if the region covers the whole map
+fn get_most_common_border_type(
+    map_grid: &MapGrid,
+    width: usize,
+    height: usize,
+    region_indices: &[usize],
+) -> Option<MapTileVisualType> {
+    let region_type = &get_tile_at_index(map_grid, width, region_indices[0]).visual_type;
+    let mut border_counts: [(MapTileVisualType, usize); 5] = [
+        (MapTileVisualType::Land, 0),
+        (MapTileVisualType::LandTree, 0),
+        (MapTileVisualType::Water, 0),
+        (MapTileVisualType::Terrain, 0),
+        (MapTileVisualType::Building, 0),
+    ];
+    for &index in region_indices {
+        for neighbor_index in get_neighbor_indices(width, height, index) {
+            let neighbor_type = &get_tile_at_index(map_grid, width, neighbor_index).visual_type;
+            if neighbor_type == region_type {
+                continue;
+            }
+            if let Some(border_count) = border_counts
+                .iter_mut()
+                .find(|(visual_type, _)| visual_type == neighbor_type)
+            {
+                border_count.1 += 1;
+            }
+        }
+    }
+    border_counts
+        .iter()
+        .filter(|(_, count)| *count > 0)
+        .reduce(|most, current| if current.1 > most.1 { current } else { most })
+        .map(|(visual_type, _)| visual_type.clone())
+}
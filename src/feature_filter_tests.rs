@@ -0,0 +1,50 @@
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use effigy_shared::game::map::{MapChunkXY, MapGrid, MapTileVisualType};
+
+use super::map_gen::MapGenerator;
+use super::regions::{label_map_regions, RegionConnectivity};
+
+fn get_smallest_area(grid: &MapGrid, visual_type: &MapTileVisualType) -> Option<usize> {
+    label_map_regions(grid, RegionConnectivity::VisualType)
+        .get_regions()
+        .iter()
+        .filter(|region| region.visual_type == *visual_type)
+        .map(|region| region.area)
+        .min()
+}
+
+#[test]
+fn generate_with_min_feature_size() {
+    let seed: u32 = 453537;
+    let map_chunks_size = MapChunkXY { x: 4, y: 4 };
+    let generator = || {
+        MapGenerator::new(seed, &map_chunks_size)
+            .with_terrain(0.3, 0.5)
+            .with_water(0.3, 0.5)
+            .with_trees(0.3, 0.5)
+    };
+    let grid = generator().generate();
+    assert!(get_smallest_area(&grid, &MapTileVisualType::Water).unwrap() < 16);
+    assert!(get_smallest_area(&grid, &MapTileVisualType::LandTree).unwrap() < 8);
+
+    let generated = generator()
+        .with_min_feature_size(MapTileVisualType::Water, 16, Some(MapTileVisualType::Land))
+        .with_min_feature_size(MapTileVisualType::LandTree, 8, None)
+        .generate_full();
+    let grid = &generated.map_grid;
+    let stats = &generated.feature_size_stats;
+    assert_eq!(stats.len(), 2);
+
+    assert!(stats[0].removed_regions_count > 0);
+    assert_eq!(
+        stats[0].smallest_kept_area,
+        get_smallest_area(grid, &MapTileVisualType::Water)
+    );
+    assert!(stats[0].smallest_kept_area.unwrap() >= 16);
+
+    // Small tree clusters take on whatever surrounds them, which can join them to other trees
+    assert!(stats[1].removed_regions_count > 0);
+    assert!(get_smallest_area(grid, &MapTileVisualType::LandTree).unwrap() >= 8);
+}
@@ -0,0 +1,143 @@
+//! Helpers for addressing tiles of a `MapGrid` by their row-major index, used by passes which
+//! keep per-tile state in flat vectors.
+
+use effigy_shared::game::map::{
+    MapGrid, MapTile, MapTileIndex, MapTileVisualType, MapTileXY, SquareBounds,
+};
+
+/// Direction from a tile to one of its 4 neighbors, named after the shared `SquareBounds` edge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Edge {
+    Left,
+    Right,
+    Down,
+    Up,
+}
+impl Edge {
+    pub(crate) const fn opposite(&self) -> Edge {
+        match self {
+            Edge::Left => Edge::Right,
+            Edge::Right => Edge::Left,
+            Edge::Down => Edge::Up,
+            Edge::Up => Edge::Down,
+        }
+    }
+
+    pub(crate) const fn is_blocked(&self, bounds: &SquareBounds) -> bool {
+        match self {
+            Edge::Left => bounds.left,
+            Edge::Right => bounds.right,
+            Edge::Down => bounds.down,
+            Edge::Up => bounds.up,
+        }
+    }
+}
+
+/// Every tile type, in declaration order
+pub(crate) const VISUAL_TYPES: [MapTileVisualType; 5] = [
+    MapTileVisualType::Land,
+    MapTileVisualType::LandTree,
+    MapTileVisualType::Water,
+    MapTileVisualType::Terrain,
+    MapTileVisualType::Building,
+];
+
+/// Character of a tile type in text maps and prefab rows:
+/// - `.` land
+/// - `T` tree
+/// - `~` water
+/// - `#` terrain
+/// - `B` building
+pub(crate) const fn get_tile_char(visual_type: &MapTileVisualType) -> char {
+    match visual_type {
+        MapTileVisualType::Land => '.',
+        MapTileVisualType::LandTree => 'T',
+        MapTileVisualType::Water => '~',
+        MapTileVisualType::Terrain => '#',
+        MapTileVisualType::Building => 'B',
+    }
+}
+
+pub(crate) const fn get_visual_type_from_char(character: char) -> Option<MapTileVisualType> {
+    match character {
+        '.' => Some(MapTileVisualType::Land),
+        'T' => Some(MapTileVisualType::LandTree),
+        '~' => Some(MapTileVisualType::Water),
+        '#' => Some(MapTileVisualType::Terrain),
+        'B' => Some(MapTileVisualType::Building),
+        _ => None,
+    }
+}
+
+pub(crate) const NO_BLOCKED_EDGES: SquareBounds = SquareBounds {
+    left: false,
+    right: false,
+    up: false,
+    down: false,
+};
+
+pub(crate) fn get_tile_xy_at_index(width: usize, index: usize) -> MapTileXY {
+    MapTileXY {
+        x: (index % width) as MapTileIndex,
+        y: (index / width) as MapTileIndex,
+    }
+}
+
+pub(crate) fn get_tile_at_index(map_grid: &MapGrid, width: usize, index: usize) -> &MapTile {
+    map_grid
+        .get_tile(&get_tile_xy_at_index(width, index))
+        .unwrap()
+}
+
+pub(crate) fn get_tile_at_index_mut(
+    map_grid: &mut MapGrid,
+    width: usize,
+    index: usize,
+) -> &mut MapTile {
+    map_grid
+        .get_tile_mut(&get_tile_xy_at_index(width, index))
+        .unwrap()
+}
+
+/// Indices of the left, right, down and up neighbors of a tile, in that order
+pub(crate) fn get_neighbor_indices(
+    width: usize,
+    height: usize,
+    index: usize,
+) -> impl Iterator<Item = usize> {
+    get_neighbor_edges(width, height, index).map(|(neighbor_index, _)| neighbor_index)
+}
+
+/// Indices of the left, right, down and up neighbors of a tile along with the edge shared with
+/// each, in that order
+pub(crate) fn get_neighbor_edges(
+    width: usize,
+    height: usize,
+    index: usize,
+) -> impl Iterator<Item = (usize, Edge)> {
+    let (x, y) = (index % width, index / width);
+    [
+        (x > 0).then(|| (index - 1, Edge::Left)),
+        (x + 1 < width).then(|| (index + 1, Edge::Right)),
+        (y > 0).then(|| (index - width, Edge::Down)),
+        (y + 1 < height).then(|| (index + width, Edge::Up)),
+    ]
+    .into_iter()
+    .flatten()
+}
+
+/// Whether a unit can step from a tile into its neighbor across the shared edge, which only needs
+/// the side of the tile being left unblocked. Edges blocked on one side only are one-way.
+/// See: `effigy_gen::walkability::WalkabilityRules`
+pub(crate) fn is_edge_walkable(from_tile: &MapTile, edge: Edge) -> bool {
+    !edge.is_blocked(&from_tile.walkability_blocked)
+}
+
+/// Whether a unit can cross the edge between two neighboring tiles in both directions
+pub(crate) fn is_edge_walkable_both_ways(
+    from_tile: &MapTile,
+    to_tile: &MapTile,
+    edge: Edge,
+) -> bool {
+    is_edge_walkable(from_tile, edge) && is_edge_walkable(to_tile, edge.opposite())
+}
@@ -0,0 +1,97 @@
+use std::ops::Range;
+
+use noise::utils::NoiseMap;
+
+use effigy_shared::game::map::{
+    MapChunkXY, MapGrid, MapTileIndex, MapTileVisualType, MapTileXY, MAP_CHUNK_TILES_LENGTH,
+};
+
+/// Normalized height band of water tiles (deeper water is lower)
+pub const WATER_HEIGHT_BAND: Range<f32> = 0.0..0.2;
+/// Normalized height band of walkable land and anything built or grown on it
+pub const LAND_HEIGHT_BAND: Range<f32> = 0.25..0.6;
+/// Normalized height band of obstacle terrain
+pub const TERRAIN_HEIGHT_BAND: Range<f32> = 0.65..1.0;
+
+const fn get_height_band_from_map_visual_type(visual_type: &MapTileVisualType) -> Range<f32> {
+    match visual_type {
+        MapTileVisualType::Water => WATER_HEIGHT_BAND,
+        MapTileVisualType::Land | MapTileVisualType::LandTree | MapTileVisualType::Building => {
+            LAND_HEIGHT_BAND
+        }
+        MapTileVisualType::Terrain => TERRAIN_HEIGHT_BAND,
+    }
+}
+
+/// Per-tile elevation of a generated map, normalized to `0.0..=1.0`.
+///
+/// Heights are stored row-major over the whole map, matching the tile layout of the `MapGrid`
+/// they were generated for.
+pub struct MapHeightmap {
+    tiles_bounds: MapTileXY,
+    heights: Vec<f32>,
+}
+impl MapHeightmap {
+    /// Maps the continuous elevation noise into the height band of each tile's visual type, so
+    /// terrain is always above land and land is always above water.
+    pub(crate) fn from_map_grid(map_grid: &MapGrid, elevation_noise: &NoiseMap) -> Self {
+        let width = map_grid.get_tiles_bounds().x as usize;
+        let height = map_grid.get_tiles_bounds().y as usize;
+        assert_eq!(
+            elevation_noise.size(),
+            (width, height),
+            "Expected elevation noise map to match the size of the map grid"
+        );
+
+        let mut heights = Vec::with_capacity(map_grid.get_tiles_count());
+        for y in 0..height {
+            for x in 0..width {
+                let tile = map_grid
+                    .get_tile(&MapTileXY {
+                        x: x as MapTileIndex,
+                        y: y as MapTileIndex,
+                    })
+                    .unwrap();
+                let band = get_height_band_from_map_visual_type(&tile.visual_type);
+                let t = ((elevation_noise.get_value(x, y) * 0.5 + 0.5).clamp(0.0, 1.0)) as f32;
+                heights.push(band.start + t * (band.end - band.start));
+            }
+        }
+
+        MapHeightmap {
+            tiles_bounds: map_grid.get_tiles_bounds(),
+            heights,
+        }
+    }
+
+    pub fn get_tiles_bounds(&self) -> &MapTileXY {
+        &self.tiles_bounds
+    }
+
+    /// Returns the height of the tile, or `None` if the tile is out of bounds
+    pub fn get_height(&self, tile_xy: &MapTileXY) -> Option<f32> {
+        if tile_xy.x >= self.tiles_bounds.x || tile_xy.y >= self.tiles_bounds.y {
+            return None;
+        }
+        let index = (tile_xy.y as usize) * (self.tiles_bounds.x as usize) + (tile_xy.x as usize);
+        Some(self.heights[index])
+    }
+
+    /// Returns the heights of a single chunk, row-major with `MAP_CHUNK_TILES_LENGTH` tiles per
+    /// row, or `None` if the chunk is out of bounds
+    pub fn get_chunk_heights(&self, chunk_xy: &MapChunkXY) -> Option<Vec<f32>> {
+        let chunk_length = MAP_CHUNK_TILES_LENGTH as usize;
+        let origin_x = (chunk_xy.x as usize) * chunk_length;
+        let origin_y = (chunk_xy.y as usize) * chunk_length;
+        if origin_x >= self.tiles_bounds.x as usize || origin_y >= self.tiles_bounds.y as usize {
+            return None;
+        }
+
+        let mut chunk_heights = Vec::with_capacity(chunk_length * chunk_length);
+        for y in origin_y..(origin_y + chunk_length) {
+            let row_start = y * (self.tiles_bounds.x as usize) + origin_x;
+            chunk_heights.extend_from_slice(&self.heights[row_start..(row_start + chunk_length)]);
+        }
+        Some(chunk_heights)
+    }
+}
@@ -0,0 +1,106 @@
+use std::fmt;
+use std::path::Path;
+
+use image::{ImageError, RgbImage};
+
+use effigy_shared::game::map::{
+    MapChunkXY, MapGrid, MapTileIndex, MapTileVisualType, MapTileXY, MAP_CHUNK_TILES_LENGTH,
+};
+
+use super::map_as_image::{get_walkable_rgb8, MapImagePalette, Rgb8};
+use crate::grid_index::VISUAL_TYPES;
+use crate::map_gen::simplify_map_walkability_blockage;
+use crate::walkability::WalkabilityRules;
+
+#[derive(Debug)]
+pub enum MapImageError {
+    Image(ImageError),
+    /// The image isn't a whole number of chunks wide and high
+    UnalignedSize {
+        width: u32,
+        height: u32,
+    },
+    /// A pixel isn't any tile color of the palette
+    UnknownColor {
+        x: u32,
+        y: u32,
+        rgb: Rgb8,
+    },
+}
+impl fmt::Display for MapImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapImageError::Image(error) => write!(f, "failed to read map image: {}", error),
+            MapImageError::UnalignedSize { width, height } => write!(
+                f,
+                "map image size {}x{} isn't a multiple of the chunk size {}",
+                width, height, MAP_CHUNK_TILES_LENGTH
+            ),
+            MapImageError::UnknownColor { x, y, rgb } => write!(
+                f,
+                "pixel ({}, {}) has color {:?}, which isn't a tile color",
+                x, y, rgb
+            ),
+        }
+    }
+}
+impl std::error::Error for MapImageError {}
+impl From<ImageError> for MapImageError {
+    fn from(error: ImageError) -> Self {
+        MapImageError::Image(error)
+    }
+}
+
+/// Tile type painted with the color, in either of the shades `save_map_grid_as_image` uses
+fn get_map_visual_type_from_rgb8(rgb: Rgb8) -> Option<MapTileVisualType> {
+    VISUAL_TYPES
+        .iter()
+        .find(|visual_type| {
+            let type_rgb = MapImagePalette::DEFAULT.get_rgb8(visual_type);
+            rgb == type_rgb || rgb == get_walkable_rgb8(type_rgb)
+        })
+        .cloned()
+}
+
+/// Loads a map painted with the palette of `save_map_grid_as_image`, e.g. by hand in an image
+/// editor. See: `map_grid_from_rgb_image`
+pub fn load_map_grid_from_image(
+    image_path: impl AsRef<Path>,
+    rules: &WalkabilityRules,
+) -> Result<MapGrid, MapImageError> {
+    let image = image::open(image_path)?.to_rgb8();
+    map_grid_from_rgb_image(&image, rules)
+}
+
+/// Builds a map with a tile per pixel, from the top-left, with each tile type painted in its
+/// palette color. The darker shade of walkable tiles is accepted, but walkability is decided by
+/// the rules for the painted tile types, like for a generated map.
+pub fn map_grid_from_rgb_image(
+    image: &RgbImage,
+    rules: &WalkabilityRules,
+) -> Result<MapGrid, MapImageError> {
+    let (width, height) = image.dimensions();
+    let chunk_length = MAP_CHUNK_TILES_LENGTH as u32;
+    if width == 0 || height == 0 || width % chunk_length != 0 || height % chunk_length != 0 {
+        return Err(MapImageError::UnalignedSize { width, height });
+    }
+
+    let mut map_grid = MapGrid::new(&MapChunkXY {
+        x: (width / chunk_length) as _,
+        y: (height / chunk_length) as _,
+    });
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let visual_type = get_map_visual_type_from_rgb8(pixel.0)
+            .ok_or(MapImageError::UnknownColor { x, y, rgb: pixel.0 })?;
+        map_grid
+            .get_tile_mut(&MapTileXY {
+                x: x as MapTileIndex,
+                y: y as MapTileIndex,
+            })
+            .unwrap()
+            .set_visual_type(&visual_type);
+    }
+
+    simplify_map_walkability_blockage(&mut map_grid, rules, false);
+    Ok(map_grid)
+}
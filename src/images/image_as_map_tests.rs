@@ -0,0 +1,68 @@
+use image::{Rgb, RgbImage};
+
+use effigy_shared::game::map::{MapChunkXY, MapTileVisualType, MapTileXY, MAP_CHUNK_TILES_LENGTH};
+
+use super::image_as_map::{load_map_grid_from_image, map_grid_from_rgb_image, MapImageError};
+use super::map_as_image::save_map_grid_as_image;
+use crate::map_gen::MapGenerator;
+use crate::walkability::WalkabilityRules;
+
+#[test]
+fn load_saved_map_image() {
+    let grid = MapGenerator::new(453537, &MapChunkXY { x: 2, y: 2 })
+        .with_terrain(0.2, 0.5)
+        .with_water(0.2, 0.5)
+        .with_trees(0.2, 0.5)
+        .generate();
+    let image_path = save_map_grid_as_image(&grid, "image_as_map_453537").unwrap();
+
+    let loaded = load_map_grid_from_image(&image_path, &WalkabilityRules::default()).unwrap();
+    assert_eq!(loaded.get_chunks_bounds(), grid.get_chunks_bounds());
+    let tiles_bounds = grid.get_tiles_bounds();
+    for y in 0..tiles_bounds.y {
+        for x in 0..tiles_bounds.x {
+            let tile = grid.get_tile(&MapTileXY { x, y }).unwrap();
+            let loaded_tile = loaded.get_tile(&MapTileXY { x, y }).unwrap();
+            assert_eq!(tile.visual_type, loaded_tile.visual_type);
+            assert_eq!(tile.walkability_blocked, loaded_tile.walkability_blocked);
+        }
+    }
+}
+
+#[test]
+fn map_from_painted_image() {
+    let length = MAP_CHUNK_TILES_LENGTH as u32;
+    let mut image = RgbImage::new(length * 2, length);
+    // A lake in the first chunk, and a darker walkable tree in the second
+    for y in 2..6 {
+        for x in 2..6 {
+            image.put_pixel(x, y, Rgb([0, 0, 255]));
+        }
+    }
+    image.put_pixel(length + 1, 1, Rgb([0, 170, 0]));
+
+    let grid = map_grid_from_rgb_image(&image, &WalkabilityRules::default()).unwrap();
+    assert_eq!(grid.get_chunks_bounds(), MapChunkXY { x: 2, y: 1 });
+    let get_visual_type = |x, y| {
+        grid.get_tile(&MapTileXY { x, y })
+            .unwrap()
+            .visual_type
+            .clone()
+    };
+    assert_eq!(get_visual_type(3, 3), MapTileVisualType::Water);
+    assert_eq!(get_visual_type(length + 1, 1), MapTileVisualType::LandTree);
+    assert_eq!(get_visual_type(0, 0), MapTileVisualType::Land);
+
+    image.put_pixel(7, 7, Rgb([12, 34, 56]));
+    assert!(matches!(
+        map_grid_from_rgb_image(&image, &WalkabilityRules::default()),
+        Err(MapImageError::UnknownColor { x: 7, y: 7, .. })
+    ));
+    assert!(matches!(
+        map_grid_from_rgb_image(
+            &RgbImage::new(length + 1, length),
+            &WalkabilityRules::default()
+        ),
+        Err(MapImageError::UnalignedSize { .. })
+    ));
+}
@@ -1,84 +1,293 @@
+use std::io::Cursor;
 use std::path::{Path, PathBuf};
 use std::{fs, io};
 
-use image::{self, ColorType};
+use image::{self, ImageBuffer, ImageFormat, Rgb, RgbImage};
 
-use effigy_shared::game::map::{MapGrid, MapTile, MapTileVisualType, MapTileXY};
+use effigy_shared::game::map::{
+    MapGrid, MapTile, MapTileVisualType, MapTileXY, MAP_CHUNK_TILES_LENGTH,
+};
 
 const BYTES_PER_TILE_PIXEL: usize = 3;
-type Rgb8 = [u8; BYTES_PER_TILE_PIXEL];
-const TILE_PIXEL_COLOR_TYPE: ColorType = ColorType::Rgb8;
+/// Fewest pixels per tile at which blocked edges leave room for the tile's own color
+const MIN_EDGE_PIXELS_PER_TILE: u32 = 3;
+/// Pixels between a chunk's corner and its coordinate label
+const CHUNK_LABEL_MARGIN: u32 = 2;
+const GLYPH_WIDTH: u32 = 3;
+const GLYPH_HEIGHT: u32 = 5;
+pub type Rgb8 = [u8; BYTES_PER_TILE_PIXEL];
 
-const fn get_rgb8_from_map_tile(tile: &MapTile) -> Rgb8 {
-    get_rgb8_from_map_visual_type(&tile.visual_type)
+/// Color of each tile type in a rendered map
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapImagePalette {
+    pub land: Rgb8,
+    pub land_tree: Rgb8,
+    pub water: Rgb8,
+    pub terrain: Rgb8,
+    pub building: Rgb8,
 }
+impl MapImagePalette {
+    /// Black land, green trees, blue water, white terrain and red buildings
+    pub const DEFAULT: MapImagePalette = MapImagePalette {
+        land: [0, 0, 0],
+        land_tree: [0, 255, 0],
+        water: [0, 0, 255],
+        terrain: [255, 255, 255],
+        building: [255, 0, 0],
+    };
 
-const fn get_rgb8_from_map_visual_type(visual_type: &MapTileVisualType) -> Rgb8 {
-    match visual_type {
-        MapTileVisualType::Land => [0, 0, 0],
-        MapTileVisualType::LandTree => [0, 255, 0],
-        MapTileVisualType::Water => [0, 0, 255],
-        MapTileVisualType::Terrain => [255, 255, 255],
-        MapTileVisualType::Building => [255, 0, 0],
+    pub const fn get_rgb8(&self, visual_type: &MapTileVisualType) -> Rgb8 {
+        match visual_type {
+            MapTileVisualType::Land => self.land,
+            MapTileVisualType::LandTree => self.land_tree,
+            MapTileVisualType::Water => self.water,
+            MapTileVisualType::Terrain => self.terrain,
+            MapTileVisualType::Building => self.building,
+        }
+    }
+}
+impl Default for MapImagePalette {
+    fn default() -> Self {
+        MapImagePalette::DEFAULT
     }
 }
 
-pub fn save_map_grid_as_image(grid: &MapGrid, image_file_name: &str) -> Result<PathBuf, io::Error> {
-    let target_dir = Path::new("example_images/");
+/// How a map is rendered to an image.
+/// See: `render_map_grid_image`
+#[derive(Debug, Clone)]
+pub struct MapImageOptions {
+    pub palette: MapImagePalette,
+    /// Width and height of the square of pixels drawn for each tile
+    pub pixels_per_tile: u32,
+    /// Recolors tiles other than land which have no blocked edges, e.g. building doors, or `None`
+    /// to draw them in their palette color
+    pub walkable_shade: Option<fn(Rgb8) -> Rgb8>,
+    /// Draws each blocked side of a tile as a line along that side, inside the tile, so one-way
+    /// edges only show on the side that's blocked. Needs at least 3 pixels per tile.
+    ///
+    /// Images are drawn from the top-left, so the `up` side of a tile, towards higher y, is its
+    /// bottom row of pixels.
+    pub blocked_edge_color: Option<Rgb8>,
+    /// Draws the left and top border of every chunk, labelled with its chunk coordinate if the
+    /// chunk is large enough to fit it
+    pub chunk_grid_color: Option<Rgb8>,
+    /// Draws a line around the outer edge of the map
+    pub map_edge_color: Option<Rgb8>,
+}
+impl Default for MapImageOptions {
+    fn default() -> Self {
+        MapImageOptions {
+            palette: MapImagePalette::DEFAULT,
+            pixels_per_tile: 1,
+            walkable_shade: Some(get_walkable_rgb8),
+            blocked_edge_color: None,
+            chunk_grid_color: None,
+            map_edge_color: None,
+        }
+    }
+}
 
-    // Create the target directory if it doesn't exist
-    if !target_dir.exists() {
-        fs::create_dir(target_dir)?;
+/// Darker shade of a tile's color, marking tiles other than land which aren't fully blocked
+pub const fn get_walkable_rgb8(rgb: Rgb8) -> Rgb8 {
+    [rgb[0] / 3 * 2, rgb[1] / 3 * 2, rgb[2] / 3 * 2]
+}
+
+fn get_rgb8_from_map_tile(tile: &MapTile, options: &MapImageOptions) -> Rgb8 {
+    let tile_rgb = options.palette.get_rgb8(&tile.visual_type);
+    match options.walkable_shade {
+        Some(walkable_shade)
+            if tile.visual_type != MapTileVisualType::Land
+                && !tile.walkability_blocked.is_any() =>
+        {
+            walkable_shade(tile_rgb)
+        }
+        _ => tile_rgb,
     }
+}
 
-    let image_bytes: Vec<u8> = {
-        let mut bytes = Vec::with_capacity(grid.get_tiles_count() * BYTES_PER_TILE_PIXEL);
-        for y in 0..grid.get_tiles_bounds().y {
-            for x in 0..grid.get_tiles_bounds().x {
-                let tile = grid.get_tile(&MapTileXY { x, y }).unwrap();
-                let (r, g, b) = {
-                    let mut tile_rgb = get_rgb8_from_map_tile(tile);
-                    if tile.visual_type != MapTileVisualType::Land
-                        && !tile.walkability_blocked.is_any()
-                    {
-                        tile_rgb[0] = tile_rgb[0] / 3 * 2;
-                        tile_rgb[1] = tile_rgb[1] / 3 * 2;
-                        tile_rgb[2] = tile_rgb[2] / 3 * 2;
-                    }
-                    (tile_rgb[0], tile_rgb[1], tile_rgb[2])
-                };
-                bytes.push(r);
-                bytes.push(g);
-                bytes.push(b);
+/// Renders the map with a square of pixels per tile, from the top-left, followed by any overlays
+/// in order: blocked edges, the chunk grid, and the map edge
+pub fn render_map_grid_image(grid: &MapGrid, options: &MapImageOptions) -> RgbImage {
+    assert!(
+        options.pixels_per_tile > 0,
+        "Pixels per tile value needs to be greater than 0"
+    );
+    assert!(
+        options.blocked_edge_color.is_none() || options.pixels_per_tile >= MIN_EDGE_PIXELS_PER_TILE,
+        "Pixels per tile value needs to be at least {} to draw blocked edges",
+        MIN_EDGE_PIXELS_PER_TILE
+    );
+
+    let pixels_per_tile = options.pixels_per_tile;
+    let tiles_bounds = grid.get_tiles_bounds();
+    let tile_rgbs: Vec<Rgb8> = (0..tiles_bounds.y)
+        .flat_map(|y| (0..tiles_bounds.x).map(move |x| MapTileXY { x, y }))
+        .map(|tile_xy| get_rgb8_from_map_tile(grid.get_tile(&tile_xy).unwrap(), options))
+        .collect();
+    let mut image = ImageBuffer::from_fn(
+        tiles_bounds.x as u32 * pixels_per_tile,
+        tiles_bounds.y as u32 * pixels_per_tile,
+        |pixel_x, pixel_y| {
+            let tile_index = (pixel_y / pixels_per_tile) as usize * tiles_bounds.x as usize
+                + (pixel_x / pixels_per_tile) as usize;
+            Rgb(tile_rgbs[tile_index])
+        },
+    );
+
+    if let Some(rgb) = options.blocked_edge_color {
+        draw_blocked_edges(&mut image, grid, pixels_per_tile, rgb);
+    }
+    if let Some(rgb) = options.chunk_grid_color {
+        draw_chunk_grid(&mut image, grid, pixels_per_tile, rgb);
+    }
+    if let Some(rgb) = options.map_edge_color {
+        let (width, height) = image.dimensions();
+        fill_rect(&mut image, 0, 0, width, 1, rgb);
+        fill_rect(&mut image, 0, height - 1, width, 1, rgb);
+        fill_rect(&mut image, 0, 0, 1, height, rgb);
+        fill_rect(&mut image, width - 1, 0, 1, height, rgb);
+    }
+    image
+}
+
+fn draw_blocked_edges(image: &mut RgbImage, grid: &MapGrid, pixels_per_tile: u32, rgb: Rgb8) {
+    let tiles_bounds = grid.get_tiles_bounds();
+    for y in 0..tiles_bounds.y {
+        for x in 0..tiles_bounds.x {
+            let blocked = &grid
+                .get_tile(&MapTileXY { x, y })
+                .unwrap()
+                .walkability_blocked;
+            let (left, top) = (x as u32 * pixels_per_tile, y as u32 * pixels_per_tile);
+            let (right, bottom) = (left + pixels_per_tile - 1, top + pixels_per_tile - 1);
+            if blocked.left {
+                fill_rect(image, left, top, 1, pixels_per_tile, rgb);
+            }
+            if blocked.right {
+                fill_rect(image, right, top, 1, pixels_per_tile, rgb);
+            }
+            if blocked.down {
+                fill_rect(image, left, top, pixels_per_tile, 1, rgb);
+            }
+            if blocked.up {
+                fill_rect(image, left, bottom, pixels_per_tile, 1, rgb);
             }
         }
-        bytes
-    };
-    assert_eq!(
-        image_bytes.len(),
-        grid.get_tiles_count() * BYTES_PER_TILE_PIXEL,
-        "Expected number of bytes to equal total grid tiles times the number of bytes per pixel"
-    );
+    }
+}
 
-    let image_path = target_dir.join(image_file_name).with_extension("png");
-    match image::save_buffer(
-        &image_path,
-        &*image_bytes,
-        grid.get_tiles_bounds().x,
-        grid.get_tiles_bounds().y as u32,
-        TILE_PIXEL_COLOR_TYPE,
-    ) {
-        Ok(()) => {}
-        Err(err) => {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!(
-                    "Failed to save image buffer to file: {:?}, error: {:?}",
-                    image_path, err
-                ),
-            ));
+fn draw_chunk_grid(image: &mut RgbImage, grid: &MapGrid, pixels_per_tile: u32, rgb: Rgb8) {
+    let (width, height) = image.dimensions();
+    let chunk_pixels = MAP_CHUNK_TILES_LENGTH as u32 * pixels_per_tile;
+    let chunks_bounds = grid.get_chunks_bounds();
+    for chunk_y in 0..chunks_bounds.y {
+        for chunk_x in 0..chunks_bounds.x {
+            let (left, top) = (chunk_x as u32 * chunk_pixels, chunk_y as u32 * chunk_pixels);
+            fill_rect(image, left, top, 1, chunk_pixels.min(height - top), rgb);
+            fill_rect(image, left, top, chunk_pixels.min(width - left), 1, rgb);
+
+            let label = format!("{},{}", chunk_x, chunk_y);
+            let label_width = label.len() as u32 * (GLYPH_WIDTH + 1) - 1;
+            if label_width + 2 * CHUNK_LABEL_MARGIN <= chunk_pixels
+                && GLYPH_HEIGHT + 2 * CHUNK_LABEL_MARGIN <= chunk_pixels
+            {
+                draw_text(
+                    image,
+                    left + CHUNK_LABEL_MARGIN,
+                    top + CHUNK_LABEL_MARGIN,
+                    &label,
+                    rgb,
+                );
+            }
+        }
+    }
+}
+
+/// Draws digits and commas in a 3 by 5 pixel font, with a pixel between characters
+fn draw_text(image: &mut RgbImage, left: u32, top: u32, text: &str, rgb: Rgb8) {
+    for (index, character) in text.chars().enumerate() {
+        let glyph = get_glyph(character);
+        let glyph_left = left + index as u32 * (GLYPH_WIDTH + 1);
+        for (row, bits) in glyph.iter().enumerate() {
+            for column in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+                    image.put_pixel(glyph_left + column, top + row as u32, Rgb(rgb));
+                }
+            }
+        }
+    }
+}
+
+/// Rows of a character from the top, with a bit per pixel from the left in the lowest 3 bits
+const fn get_glyph(character: char) -> [u8; GLYPH_HEIGHT as usize] {
+    match character {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b011, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        _ => [0b000; GLYPH_HEIGHT as usize],
+    }
+}
+
+fn fill_rect(image: &mut RgbImage, left: u32, top: u32, width: u32, height: u32, rgb: Rgb8) {
+    for y in top..(top + height) {
+        for x in left..(left + width) {
+            image.put_pixel(x, y, Rgb(rgb));
         }
     }
+}
+
+/// Renders the map and encodes it as PNG, e.g. for the client to show as a minimap
+pub fn encode_map_grid_png(
+    grid: &MapGrid,
+    options: &MapImageOptions,
+) -> Result<Vec<u8>, io::Error> {
+    let mut png_bytes = Cursor::new(Vec::new());
+    match render_map_grid_image(grid, options).write_to(&mut png_bytes, ImageFormat::Png) {
+        Ok(()) => Ok(png_bytes.into_inner()),
+        Err(err) => Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("Failed to encode map image: {:?}", err),
+        )),
+    }
+}
+
+/// Renders the map and saves it as PNG at the path, whatever its extension
+pub fn save_map_grid_image(
+    grid: &MapGrid,
+    image_path: impl AsRef<Path>,
+    options: &MapImageOptions,
+) -> Result<(), io::Error> {
+    let image_path = image_path.as_ref();
+    match render_map_grid_image(grid, options).save_with_format(image_path, ImageFormat::Png) {
+        Ok(()) => Ok(()),
+        Err(err) => Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "Failed to save image buffer to file: {:?}, error: {:?}",
+                image_path, err
+            ),
+        )),
+    }
+}
+
+/// Saves the map with the default options to `example_images/`, with a PNG extension
+pub fn save_map_grid_as_image(grid: &MapGrid, image_file_name: &str) -> Result<PathBuf, io::Error> {
+    let target_dir = Path::new("example_images/");
+
+    // Create the target directory if it doesn't exist
+    if !target_dir.exists() {
+        fs::create_dir(target_dir)?;
+    }
 
+    let image_path = target_dir.join(image_file_name).with_extension("png");
+    save_map_grid_image(grid, &image_path, &MapImageOptions::default())?;
     Ok(image_path)
 }
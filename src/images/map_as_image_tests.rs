@@ -0,0 +1,101 @@
+use image::Rgb;
+
+use effigy_shared::game::map::{
+    MapChunkXY, MapGrid, MapTileVisualType, MapTileXY, SquareBounds, MAP_CHUNK_TILES_LENGTH,
+};
+
+use super::map_as_image::{
+    encode_map_grid_png, render_map_grid_image, MapImageOptions, MapImagePalette,
+};
+
+#[test]
+fn render_with_options() {
+    let mut grid = MapGrid::new(&MapChunkXY { x: 2, y: 1 });
+    grid.get_tile_mut(&MapTileXY { x: 1, y: 0 })
+        .unwrap()
+        .set_visual_type(&MapTileVisualType::Water)
+        .set_walkability_blocked(&SquareBounds::ALL);
+    // A walkable building tile, like a door
+    grid.get_tile_mut(&MapTileXY { x: 0, y: 1 })
+        .unwrap()
+        .set_visual_type(&MapTileVisualType::Building);
+
+    let default_image = render_map_grid_image(&grid, &MapImageOptions::default());
+    assert_eq!(
+        default_image.dimensions(),
+        (
+            grid.get_tiles_bounds().x as u32,
+            grid.get_tiles_bounds().y as u32
+        )
+    );
+    assert_eq!(default_image.get_pixel(1, 0), &Rgb([0, 0, 255]));
+    assert_eq!(default_image.get_pixel(0, 1), &Rgb([170, 0, 0]));
+
+    let options = MapImageOptions {
+        palette: MapImagePalette {
+            land: [30, 120, 40],
+            ..MapImagePalette::DEFAULT
+        },
+        pixels_per_tile: 4,
+        walkable_shade: None,
+        ..MapImageOptions::default()
+    };
+    let image = render_map_grid_image(&grid, &options);
+    assert_eq!(image.width(), default_image.width() * 4);
+    assert_eq!(image.get_pixel(0, 0), &Rgb([30, 120, 40]));
+    assert_eq!(image.get_pixel(7, 3), &Rgb([0, 0, 255]));
+    assert_eq!(image.get_pixel(8, 3), &Rgb([30, 120, 40]));
+    assert_eq!(image.get_pixel(3, 4), &Rgb([255, 0, 0]));
+
+    let png_bytes = encode_map_grid_png(&grid, &options).unwrap();
+    let decoded = image::load_from_memory(&png_bytes).unwrap().to_rgb8();
+    assert_eq!(decoded, image);
+}
+
+#[test]
+fn render_overlays() {
+    let mut grid = MapGrid::new(&MapChunkXY { x: 2, y: 2 });
+    // Only the side of the tile that units can't leave across is drawn
+    grid.get_tile_mut(&MapTileXY { x: 5, y: 5 })
+        .unwrap()
+        .set_walkability_blocked(&SquareBounds {
+            left: false,
+            right: true,
+            down: false,
+            up: true,
+        });
+
+    let edge_rgb = [255, 0, 255];
+    let grid_rgb = [255, 255, 0];
+    let map_edge_rgb = [0, 255, 255];
+    let options = MapImageOptions {
+        pixels_per_tile: 4,
+        blocked_edge_color: Some(edge_rgb),
+        chunk_grid_color: Some(grid_rgb),
+        map_edge_color: Some(map_edge_rgb),
+        ..MapImageOptions::default()
+    };
+    let image = render_map_grid_image(&grid, &options);
+    let land = Rgb(MapImagePalette::DEFAULT.land);
+
+    // Tile (5, 5) spans pixels 20 to 23
+    assert_eq!(image.get_pixel(23, 21), &Rgb(edge_rgb));
+    assert_eq!(image.get_pixel(21, 23), &Rgb(edge_rgb));
+    assert_eq!(image.get_pixel(20, 21), &land);
+    assert_eq!(image.get_pixel(21, 20), &land);
+    assert_eq!(image.get_pixel(21, 21), &land);
+
+    // Chunk borders, and the label of chunk (1, 1) starting with a "1"
+    let chunk_pixels = 4 * MAP_CHUNK_TILES_LENGTH as u32;
+    assert_eq!(image.get_pixel(chunk_pixels, 7), &Rgb(grid_rgb));
+    assert_eq!(image.get_pixel(7, chunk_pixels), &Rgb(grid_rgb));
+    assert_eq!(
+        image.get_pixel(chunk_pixels + 3, chunk_pixels + 2),
+        &Rgb(grid_rgb)
+    );
+    assert_eq!(image.get_pixel(chunk_pixels + 2, chunk_pixels + 2), &land);
+
+    let (width, height) = image.dimensions();
+    assert_eq!(image.get_pixel(width - 1, 9), &Rgb(map_edge_rgb));
+    assert_eq!(image.get_pixel(9, height - 1), &Rgb(map_edge_rgb));
+}
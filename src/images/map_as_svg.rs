@@ -0,0 +1,298 @@
+use std::fmt::Write;
+use std::path::Path;
+use std::{fs, io};
+
+use effigy_shared::game::map::{MapGrid, MapTileVisualType, MapTileXY};
+
+use super::map_as_image::{MapImagePalette, Rgb8};
+use crate::grid_index::VISUAL_TYPES;
+
+/// Width of a blocked edge stroke, in tiles
+const BLOCKED_EDGE_WIDTH: f64 = 0.125;
+const BLOCKED_EDGES_GROUP_ID: &str = "blocked-edges";
+const LEGEND_WIDTH: u32 = 120;
+const LEGEND_ENTRY_HEIGHT: u32 = 20;
+const LEGEND_SWATCH_SIZE: u32 = 12;
+const LEGEND_FONT_SIZE: u32 = 12;
+
+/// How a map is rendered to SVG.
+/// See: `render_map_grid_svg`
+#[derive(Debug, Clone)]
+pub struct MapSvgOptions {
+    pub palette: MapImagePalette,
+    /// Width and height of each tile at the SVG's natural size, which viewers can zoom freely
+    pub pixels_per_tile: u32,
+    /// Strokes each blocked side of a tile along the inside of that side, so one-way edges only
+    /// show on the side that's blocked
+    pub blocked_edge_color: Option<Rgb8>,
+    /// Adds a legend to the right of the map, where clicking an entry shows or hides its group.
+    /// Scripts only run when the SVG is opened directly, not through an `<img>` tag.
+    pub with_legend: bool,
+}
+impl Default for MapSvgOptions {
+    fn default() -> Self {
+        MapSvgOptions {
+            palette: MapImagePalette::DEFAULT,
+            pixels_per_tile: 8,
+            blocked_edge_color: Some([255, 0, 255]),
+            with_legend: true,
+        }
+    }
+}
+
+/// Id of the group holding the regions of a tile type
+pub const fn get_svg_group_id(visual_type: &MapTileVisualType) -> &'static str {
+    match visual_type {
+        MapTileVisualType::Land => "land",
+        MapTileVisualType::LandTree => "land-tree",
+        MapTileVisualType::Water => "water",
+        MapTileVisualType::Terrain => "terrain",
+        MapTileVisualType::Building => "building",
+    }
+}
+
+/// Renders the map as SVG, from the top-left like `render_map_grid_image`, with a group per tile
+/// type followed by a group of blocked edges, each with the id of its layer so it can be toggled.
+///
+/// Every connected region of a tile type is a single polygon, holes included, so the file stays
+/// small and zooms without seams between tiles.
+pub fn render_map_grid_svg(grid: &MapGrid, options: &MapSvgOptions) -> String {
+    assert!(
+        options.pixels_per_tile > 0,
+        "Pixels per tile value needs to be greater than 0"
+    );
+
+    let tiles_bounds = grid.get_tiles_bounds();
+    let (width, height) = (tiles_bounds.x as u32, tiles_bounds.y as u32);
+    let map_width = width * options.pixels_per_tile;
+    let map_height = height * options.pixels_per_tile;
+    let (svg_width, svg_height) = if options.with_legend {
+        let legend_height = get_legend_entries_count(options) as u32 * LEGEND_ENTRY_HEIGHT;
+        (map_width + LEGEND_WIDTH, map_height.max(legend_height))
+    } else {
+        (map_width, map_height)
+    };
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+        svg_width, svg_height
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        r#"<g id="map" transform="scale({})" shape-rendering="crispEdges">"#,
+        options.pixels_per_tile
+    )
+    .unwrap();
+    for visual_type in VISUAL_TYPES.iter() {
+        writeln!(
+            svg,
+            r#"<g id="{}" fill="{}">"#,
+            get_svg_group_id(visual_type),
+            get_svg_color(options.palette.get_rgb8(visual_type))
+        )
+        .unwrap();
+        let path = get_region_outlines_path(grid, visual_type);
+        if !path.is_empty() {
+            writeln!(svg, r#"<path fill-rule="evenodd" d="{}"/>"#, path).unwrap();
+        }
+        svg.push_str("</g>\n");
+    }
+    if let Some(rgb) = options.blocked_edge_color {
+        writeln!(
+            svg,
+            r#"<g id="{}" fill="none" stroke="{}" stroke-width="{}">"#,
+            BLOCKED_EDGES_GROUP_ID,
+            get_svg_color(rgb),
+            BLOCKED_EDGE_WIDTH
+        )
+        .unwrap();
+        let path = get_blocked_edges_path(grid);
+        if !path.is_empty() {
+            writeln!(svg, r#"<path d="{}"/>"#, path).unwrap();
+        }
+        svg.push_str("</g>\n");
+    }
+    svg.push_str("</g>\n");
+
+    if options.with_legend {
+        write_legend(&mut svg, map_width, options);
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Renders the map and saves it as SVG at the path, whatever its extension
+pub fn save_map_grid_svg(
+    grid: &MapGrid,
+    svg_path: impl AsRef<Path>,
+    options: &MapSvgOptions,
+) -> Result<(), io::Error> {
+    fs::write(svg_path, render_map_grid_svg(grid, options))
+}
+
+fn get_svg_color(rgb: Rgb8) -> String {
+    format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2])
+}
+
+/// Path data of the outlines of every region of the tile type, in tiles.
+///
+/// Each side between a tile of the type and any other tile, or the edge of the map, is an edge
+/// going clockwise around the tile. Chaining those edges end to start gives closed outlines, and
+/// with the even-odd fill rule it doesn't matter how outlines touching at a corner are split, as
+/// the same edges are crossed either way.
+fn get_region_outlines_path(grid: &MapGrid, visual_type: &MapTileVisualType) -> String {
+    let tiles_bounds = grid.get_tiles_bounds();
+    let (width, height) = (tiles_bounds.x as usize, tiles_bounds.y as usize);
+    let is_type = |x: usize, y: usize| -> bool {
+        grid.get_tile(&MapTileXY {
+            x: x as _,
+            y: y as _,
+        })
+        .unwrap()
+        .visual_type
+            == *visual_type
+    };
+
+    // Edges leaving each corner between tiles, indexed by corner row-major
+    let corner_index = |x: usize, y: usize| y * (width + 1) + x;
+    let mut outgoing: Vec<Vec<usize>> = vec![Vec::new(); (width + 1) * (height + 1)];
+    for y in 0..height {
+        for x in 0..width {
+            if !is_type(x, y) {
+                continue;
+            }
+            if y == 0 || !is_type(x, y - 1) {
+                outgoing[corner_index(x, y)].push(corner_index(x + 1, y));
+            }
+            if x + 1 == width || !is_type(x + 1, y) {
+                outgoing[corner_index(x + 1, y)].push(corner_index(x + 1, y + 1));
+            }
+            if y + 1 == height || !is_type(x, y + 1) {
+                outgoing[corner_index(x + 1, y + 1)].push(corner_index(x, y + 1));
+            }
+            if x == 0 || !is_type(x - 1, y) {
+                outgoing[corner_index(x, y + 1)].push(corner_index(x, y));
+            }
+        }
+    }
+
+    let mut path = String::new();
+    for start in 0..outgoing.len() {
+        while !outgoing[start].is_empty() {
+            let mut outline = vec![start];
+            let mut corner = outgoing[start].pop().unwrap();
+            while corner != start {
+                outline.push(corner);
+                corner = outgoing[corner]
+                    .pop()
+                    .expect("Expected region outline edges to form closed loops");
+            }
+
+            // Only corners where the outline turns are kept
+            let corner_xy = |index: usize| (index % (width + 1), index / (width + 1));
+            let turns: Vec<(usize, usize)> = (0..outline.len())
+                .map(|i| {
+                    (
+                        corner_xy(outline[(i + outline.len() - 1) % outline.len()]),
+                        corner_xy(outline[i]),
+                        corner_xy(outline[(i + 1) % outline.len()]),
+                    )
+                })
+                .filter(|(previous, current, next)| {
+                    !(previous.0 == current.0 && current.0 == next.0
+                        || previous.1 == current.1 && current.1 == next.1)
+                })
+                .map(|(_, current, _)| current)
+                .collect();
+            write!(path, "M{} {}", turns[0].0, turns[0].1).unwrap();
+            for pair in turns.windows(2) {
+                if pair[0].0 == pair[1].0 {
+                    write!(path, "V{}", pair[1].1).unwrap();
+                } else {
+                    write!(path, "H{}", pair[1].0).unwrap();
+                }
+            }
+            path.push('Z');
+        }
+    }
+    path
+}
+
+/// Path data of a line along the inside of every blocked side, in tiles
+fn get_blocked_edges_path(grid: &MapGrid) -> String {
+    let inset = BLOCKED_EDGE_WIDTH / 2.0;
+    let tiles_bounds = grid.get_tiles_bounds();
+    let mut path = String::new();
+    for y in 0..tiles_bounds.y {
+        for x in 0..tiles_bounds.x {
+            let blocked = &grid
+                .get_tile(&MapTileXY { x, y })
+                .unwrap()
+                .walkability_blocked;
+            let (left, top) = (x as f64, y as f64);
+            if blocked.left {
+                write!(path, "M{} {}v1", left + inset, top).unwrap();
+            }
+            if blocked.right {
+                write!(path, "M{} {}v1", left + 1.0 - inset, top).unwrap();
+            }
+            // The `up` side of a tile, towards higher y, is its bottom side
+            if blocked.down {
+                write!(path, "M{} {}h1", left, top + inset).unwrap();
+            }
+            if blocked.up {
+                write!(path, "M{} {}h1", left, top + 1.0 - inset).unwrap();
+            }
+        }
+    }
+    path
+}
+
+fn get_legend_entries_count(options: &MapSvgOptions) -> usize {
+    VISUAL_TYPES.len() + usize::from(options.blocked_edge_color.is_some())
+}
+
+fn write_legend(svg: &mut String, left: u32, options: &MapSvgOptions) {
+    svg.push_str(
+        "<script>function toggleLayer(id) { var layer = document.getElementById(id); \
+         layer.style.display = layer.style.display === 'none' ? '' : 'none'; }</script>\n",
+    );
+    writeln!(
+        svg,
+        r#"<g id="legend" font-family="sans-serif" font-size="{}">"#,
+        LEGEND_FONT_SIZE
+    )
+    .unwrap();
+    let mut entries: Vec<(&str, Rgb8)> = VISUAL_TYPES
+        .iter()
+        .map(|visual_type| {
+            (
+                get_svg_group_id(visual_type),
+                options.palette.get_rgb8(visual_type),
+            )
+        })
+        .collect();
+    if let Some(rgb) = options.blocked_edge_color {
+        entries.push((BLOCKED_EDGES_GROUP_ID, rgb));
+    }
+    for (index, (group_id, rgb)) in entries.into_iter().enumerate() {
+        let top =
+            index as u32 * LEGEND_ENTRY_HEIGHT + (LEGEND_ENTRY_HEIGHT - LEGEND_SWATCH_SIZE) / 2;
+        writeln!(
+            svg,
+            r##"<g cursor="pointer" onclick="toggleLayer('{0}')"><rect x="{1}" y="{2}" width="{3}" height="{3}" fill="{4}" stroke="#808080"/><text x="{5}" y="{6}">{0}</text></g>"##,
+            group_id,
+            left + 4,
+            top,
+            LEGEND_SWATCH_SIZE,
+            get_svg_color(rgb),
+            left + 8 + LEGEND_SWATCH_SIZE,
+            top + LEGEND_SWATCH_SIZE - 1
+        )
+        .unwrap();
+    }
+    svg.push_str("</g>\n");
+}
@@ -0,0 +1,144 @@
+use effigy_shared::game::map::{MapChunkXY, MapGrid, MapTileVisualType, MapTileXY, SquareBounds};
+
+use super::map_as_svg::{get_svg_group_id, render_map_grid_svg, MapSvgOptions};
+use crate::grid_index::VISUAL_TYPES;
+use crate::map_gen::MapGenerator;
+
+/// Path data of the group with the id, or an empty string if the group has no path
+fn get_group_path<'a>(svg: &'a str, group_id: &str) -> &'a str {
+    let group_start = svg
+        .find(&format!(r#"<g id="{}""#, group_id))
+        .unwrap_or_else(|| panic!("Expected a group with id '{}'", group_id));
+    let group = &svg[group_start..];
+    let group = &group[..group.find("</g>").unwrap()];
+    match group.find(r#" d=""#) {
+        Some(path_start) => {
+            let path = &group[(path_start + 4)..];
+            &path[..path.find('"').unwrap()]
+        }
+        None => "",
+    }
+}
+
+/// Area in tiles enclosed by path data of `M`, `H`, `V` and `Z` commands, where outlines going
+/// clockwise from the top-left add to it and outlines going the other way, as holes do, subtract
+fn get_path_area(path: &str) -> i64 {
+    let mut area: i64 = 0;
+    let (mut start, mut current) = ((0, 0), (0, 0));
+    let mut commands = path
+        .char_indices()
+        .filter(|(_, c)| c.is_ascii_uppercase())
+        .peekable();
+    while let Some((index, command)) = commands.next() {
+        let end = commands.peek().map_or(path.len(), |(next, _)| *next);
+        let values: Vec<i64> = path[(index + 1)..end]
+            .split(' ')
+            .filter(|value| !value.is_empty())
+            .map(|value| value.parse().unwrap())
+            .collect();
+        let next = match command {
+            'M' => {
+                start = (values[0], values[1]);
+                current = start;
+                continue;
+            }
+            'H' => (values[0], current.1),
+            'V' => (current.0, values[0]),
+            'Z' => start,
+            _ => panic!("Unexpected path command '{}'", command),
+        };
+        area += current.0 * next.1 - next.0 * current.1;
+        current = next;
+    }
+    area / 2
+}
+
+#[test]
+fn render_regions_and_blocked_edges() {
+    let mut grid = MapGrid::new(&MapChunkXY { x: 1, y: 1 });
+    // A 3x3 pond around an island of land
+    for y in 1..4 {
+        for x in 1..4 {
+            if (x, y) != (2, 2) {
+                grid.get_tile_mut(&MapTileXY { x, y })
+                    .unwrap()
+                    .set_visual_type(&MapTileVisualType::Water);
+            }
+        }
+    }
+    grid.get_tile_mut(&MapTileXY { x: 0, y: 0 })
+        .unwrap()
+        .set_walkability_blocked(&SquareBounds {
+            left: true,
+            right: false,
+            down: false,
+            up: true,
+        });
+
+    let svg = render_map_grid_svg(&grid, &MapSvgOptions::default());
+    assert!(svg.starts_with("<svg "));
+    assert!(svg.ends_with("</svg>\n"));
+    assert_eq!(get_group_path(&svg, "water"), "M1 1H4V4H1ZM2 2V3H3V2Z");
+    assert_eq!(get_group_path(&svg, "building"), "");
+    // The land outside the pond, with the pond as a hole, and the island
+    assert_eq!(get_group_path(&svg, "land").matches('M').count(), 3);
+    assert_eq!(
+        get_group_path(&svg, "blocked-edges"),
+        "M0.0625 0v1M0 0.9375h1"
+    );
+    assert!(svg.contains("toggleLayer('water')"));
+
+    let options = MapSvgOptions {
+        blocked_edge_color: None,
+        with_legend: false,
+        ..MapSvgOptions::default()
+    };
+    let svg = render_map_grid_svg(&grid, &options);
+    assert!(!svg.contains("blocked-edges") && !svg.contains("<script>"));
+}
+
+#[test]
+fn render_generated_map() {
+    let grid = MapGenerator::new(453537, &MapChunkXY { x: 2, y: 2 })
+        .with_terrain(0.2, 0.5)
+        .with_buildings(0.1, 0.25)
+        .with_water(0.2, 0.5)
+        .with_trees(0.2, 0.5)
+        .generate();
+    let svg = render_map_grid_svg(&grid, &MapSvgOptions::default());
+    for visual_type in VISUAL_TYPES {
+        let path = get_group_path(&svg, get_svg_group_id(&visual_type));
+        let tiles_count = (0..grid.get_tiles_bounds().y)
+            .flat_map(|y| (0..grid.get_tiles_bounds().x).map(move |x| MapTileXY { x, y }))
+            .filter(|tile_xy| grid.get_tile(tile_xy).unwrap().visual_type == visual_type)
+            .count();
+        assert_eq!(get_path_area(path), tiles_count as i64);
+    }
+}
+
+#[test]
+fn render_checkerboard_pinch_corners() {
+    let mut grid = MapGrid::new(&MapChunkXY { x: 1, y: 1 });
+    // Water tiles touching only at their corners, as on a checkerboard
+    for (x, y) in [(0, 0), (1, 1), (2, 0)] {
+        grid.get_tile_mut(&MapTileXY { x, y })
+            .unwrap()
+            .set_visual_type(&MapTileVisualType::Water);
+    }
+    let options = MapSvgOptions {
+        pixels_per_tile: 1,
+        ..MapSvgOptions::default()
+    };
+    let svg = render_map_grid_svg(&grid, &options);
+    assert_eq!(
+        get_group_path(&svg, "water"),
+        "M0 0H1V1H2V2H1V1H0ZM2 0H3V1H2Z"
+    );
+
+    // The legend is taller than the map
+    let tiles_bounds = grid.get_tiles_bounds();
+    assert!(svg.starts_with(&format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="120" viewBox="0 0 {0} 120">"#,
+        tiles_bounds.x + 120
+    )));
+}
@@ -1,4 +1,12 @@
+pub mod image_as_map;
 pub mod map_as_image;
+pub mod map_as_svg;
 
+#[cfg(test)]
+mod image_as_map_tests;
 #[cfg(test)]
 mod image_examples;
+#[cfg(test)]
+mod map_as_image_tests;
+#[cfg(test)]
+mod map_as_svg_tests;
@@ -1,7 +1,58 @@
+pub mod border_gen;
+pub mod building_gen;
+pub mod chunk_codec;
+pub mod chunk_delta;
+pub mod chunk_store;
+pub mod connectivity;
+pub mod feature_filter;
+mod grid_index;
+pub mod heightmap;
 #[cfg(feature = "images")]
 pub mod images;
 pub mod map_gen;
 pub mod noise_gen;
+pub mod pathfinding;
+pub mod points_of_interest;
+pub mod prefab;
+mod random;
+pub mod regions;
+pub mod river_gen;
+pub mod smoothing;
+pub mod spawn_points;
+pub mod text_map;
+#[cfg(feature = "tiled")]
+pub mod tiled;
+pub mod walkability;
 
+#[cfg(test)]
+mod border_gen_tests;
+#[cfg(test)]
+mod building_gen_tests;
+#[cfg(test)]
+mod chunk_codec_tests;
+#[cfg(test)]
+mod chunk_delta_tests;
+#[cfg(test)]
+mod chunk_store_tests;
+#[cfg(test)]
+mod feature_filter_tests;
 #[cfg(test)]
 mod map_gen_tests;
+#[cfg(test)]
+mod pathfinding_tests;
+#[cfg(test)]
+mod points_of_interest_tests;
+#[cfg(test)]
+mod prefab_tests;
+#[cfg(test)]
+mod regions_tests;
+#[cfg(test)]
+mod smoothing_tests;
+#[cfg(test)]
+mod spawn_points_tests;
+#[cfg(test)]
+mod text_map_tests;
+#[cfg(all(test, feature = "tiled"))]
+mod tiled_tests;
+#[cfg(test)]
+mod walkability_tests;
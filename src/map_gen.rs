@@ -5,23 +5,345 @@ use effigy_shared::game::map::{
     MapChunkXY, MapGrid, MapTileIndex, MapTileVisualType, MapTileXY, SquareBounds,
 };
 
+use noise::core::worley::ReturnType;
+use noise::utils::NoiseMap;
+
+use super::border_gen::{apply_map_border, BorderShape, BorderStyle, MapBorder};
+use super::building_gen::{
+    label_buildings, place_building_doors, separate_buildings, shape_buildings,
+    BuildingShapeOptions, MapBuildings,
+};
+use super::connectivity::{enforce_walkable_connectivity, ConnectivityMode};
+use super::feature_filter::{filter_small_features, FeatureSizeRule, FeatureSizeStats};
+use super::grid_index::{get_tile_at_index_mut, get_tile_char, VISUAL_TYPES};
+use super::heightmap::MapHeightmap;
 use super::noise_gen::{
-    generate_buildings_noise, generate_foilage_noise, generate_landscape_noise,
-    generate_water_noise,
+    build_elevation_noise, build_foilage_noise, build_landscape_noise, build_water_noise,
+    generate_styled_buildings_noise, BuildingDistanceFunction, BuildingNoiseStyle,
+};
+use super::points_of_interest::{
+    get_points_of_interest_seed, place_chunk_points_of_interest, PointOfInterest,
+    PointOfInterestKind, PointOfInterestLayer,
 };
+use super::prefab::{place_prefabs, Prefab, PrefabLayer, PrefabPlacement, PrefabSite};
+use super::random::{hash_bytes, hash_coords};
+use super::river_gen::trace_river_tiles;
+use super::smoothing::{smooth_mask, SmoothingRule};
+use super::walkability::WalkabilityRules;
+
+/// The finalized map grid along with any optional outputs requested from the `MapGenerator`
+pub struct GeneratedMap {
+    pub map_grid: MapGrid,
+    /// Present if the generator was configured `with_heightmap`
+    pub heightmap: Option<MapHeightmap>,
+    /// Points of interest of every layer added `with_points_of_interest`, ordered by layer, then
+    /// by chunk, then by tile in scan order
+    pub points_of_interest: Vec<PointOfInterest>,
+    /// Every building of the map, with a lookup from tile to building
+    pub buildings: MapBuildings,
+    /// What each rule added `with_min_feature_size` removed, in the order the rules were added
+    pub feature_size_stats: Vec<FeatureSizeStats>,
+    /// Every prefab stamped into the map, in the order they were added `with_prefab`
+    pub prefab_sites: Vec<PrefabSite>,
+}
+
+/// Bump whenever the generator produces different tiles for the same seed and parameters, so
+/// anything stored against the old output can tell. See: `MapGenerator::get_fingerprint`
+///
+/// - 2: both sides of every tile edge are set when simplifying walkability, as a side only blocks
+///   units leaving the tile across it
+pub const MAP_GENERATOR_VERSION: u32 = 2;
+
+/// Seed and parameters of a `MapGenerator`, e.g. to record alongside an exported map.
+/// See: `MapGenerator::get_description`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapGeneratorDescription {
+    pub seed: u32,
+    pub version: u32,
+    pub fingerprint: u64,
+    /// Every builder step so far with its parameters, in the order they were called
+    pub steps: Vec<String>,
+}
 
 pub struct MapGenerator {
     seed: u32,
+    /// Hash of the generator version and every builder step so far, in order
+    fingerprint: u64,
+    steps: Vec<String>,
     map_grid: MapGrid,
+    is_wrapping: bool,
+    /// Whether any layer has placed its tiles, which happens as soon as it's added
+    has_layers: bool,
+    border: Option<MapBorder>,
+    heightmap_enabled: bool,
+    connectivity_mode: Option<ConnectivityMode>,
+    feature_size_rules: Vec<FeatureSizeRule>,
+    poi_layers: Vec<PointOfInterestLayer>,
+    prefab_layers: Vec<PrefabLayer>,
+    building_shape_options: Option<BuildingShapeOptions>,
+    smoothing_rule: Option<SmoothingRule>,
+    walkability_rules: WalkabilityRules,
+    /// Building noise cell of each tile placed by the building layer, row-major, so touching
+    /// buildings from different cells stay distinct
+    building_cell_keys: Vec<Option<u64>>,
 }
 impl MapGenerator {
     pub fn new(seed: u32, map_chunks_count: &MapChunkXY) -> Self {
-        MapGenerator {
+        let map_grid = MapGrid::new(map_chunks_count);
+        let tiles_count = map_grid.get_tiles_count();
+        let mut generator = MapGenerator {
             seed,
-            map_grid: MapGrid::new(map_chunks_count),
+            fingerprint: 0,
+            steps: Vec::new(),
+            map_grid,
+            is_wrapping: false,
+            has_layers: false,
+            border: None,
+            heightmap_enabled: false,
+            connectivity_mode: None,
+            feature_size_rules: Vec::new(),
+            poi_layers: Vec::new(),
+            prefab_layers: Vec::new(),
+            building_shape_options: None,
+            smoothing_rule: None,
+            walkability_rules: WalkabilityRules::default(),
+            building_cell_keys: vec![None; tiles_count],
+        };
+        let first_step = format!(
+            "version={} seed={} chunks=({}, {})",
+            MAP_GENERATOR_VERSION, seed, map_chunks_count.x, map_chunks_count.y
+        );
+        generator.record_step(
+            format_args!("{}", first_step),
+            format_args!("{}", first_step),
+        );
+        generator
+    }
+
+    /// Identifies the map this generator produces: generators with the same fingerprint produce
+    /// the same tiles, as long as their `MAP_GENERATOR_VERSION` matches and every
+    /// `BuildingDistanceFunction::Custom` id names a single function.
+    /// See: `effigy_gen::chunk_store::ChunkStore`
+    pub fn get_fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+
+    pub fn get_description(&self) -> MapGeneratorDescription {
+        MapGeneratorDescription {
+            seed: self.seed,
+            version: MAP_GENERATOR_VERSION,
+            fingerprint: self.fingerprint,
+            // The first step only records the version, seed and size
+            steps: self.steps[1..].to_vec(),
         }
     }
 
+    /// Make the map wrap around like a torus, for arena-style game modes: noise tiles seamlessly
+    /// and walkability connects the left edge of the map to the right and the bottom to the top,
+    /// so the map tiles perfectly when repeated. Needs to be called before adding any layers.
+    ///
+    /// Only layer placement and walkability wrap, so wrap-around maps can't have buildings or
+    /// connectivity, and regions, points of interest and pathfinding still treat the map as
+    /// bounded.
+    pub fn with_wrap_around(mut self) -> Self {
+        assert!(
+            !self.has_layers,
+            "Wrap-around needs to be set before adding any layers"
+        );
+        assert!(
+            self.connectivity_mode.is_none(),
+            "Wrap-around maps can't have connectivity"
+        );
+        assert!(
+            self.border.is_none(),
+            "Wrap-around maps can't have a border"
+        );
+        self.record_step(
+            format_args!("wrap_around"),
+            format_args!("with_wrap_around()"),
+        );
+        self.is_wrapping = true;
+        self
+    }
+
+    /// Surround the playable area with a natural-looking border when generating the map, such as
+    /// an ocean or a ring of mountains, overriding any layers. Can't be used with
+    /// `with_wrap_around`. See: `MapBorder`
+    pub fn with_border(mut self, border: MapBorder) -> Self {
+        assert!(
+            border.noisiness >= 0.0 && border.noisiness <= 1.0,
+            "Noisiness value needs to be between 0.0 and 1.0 (inclusive)"
+        );
+        assert!(!self.is_wrapping, "Wrap-around maps can't have a border");
+        self.record_step(
+            format_args!("border={}", get_border_encoding(&border)),
+            format_args!("with_border({:?})", border),
+        );
+        self.border = Some(border);
+        self
+    }
+
+    /// Additionally produce a per-tile heightmap when generating the map.
+    /// See: `GeneratedMap::heightmap`
+    pub fn with_heightmap(mut self) -> Self {
+        self.record_step(format_args!("heightmap"), format_args!("with_heightmap()"));
+        self.heightmap_enabled = true;
+        self
+    }
+
+    /// Ensure players can reach all walkable land when generating the map, either by connecting
+    /// isolated regions or removing them. Walkable tiles are land and any tile type the walkability
+    /// rules open to land in both directions, such as trees with `EdgeRule::Open`, so regions are
+    /// only connected across edges units can walk both ways. See: `ConnectivityMode`
+    pub fn with_connectivity(mut self, mode: ConnectivityMode) -> Self {
+        assert!(
+            !self.is_wrapping,
+            "Wrap-around maps can't have connectivity"
+        );
+        self.record_step(
+            format_args!("connectivity={}", get_connectivity_encoding(&mode)),
+            format_args!("with_connectivity({:?})", mode),
+        );
+        self.connectivity_mode = Some(mode);
+        self
+    }
+
+    /// Decide which tile edges are blocked when generating the map from the tile types on either
+    /// side of each edge, instead of only opening edges between tiles of the same type.
+    /// See: `WalkabilityRules`
+    pub fn with_walkability_rules(mut self, rules: WalkabilityRules) -> Self {
+        self.record_step(
+            format_args!("walkability_rules={}", get_walkability_encoding(&rules)),
+            format_args!("with_walkability_rules({:?})", rules),
+        );
+        self.walkability_rules = rules;
+        self
+    }
+
+    /// Remove every connected feature of the tile type smaller than `min_area` tiles when
+    /// generating the map, replacing it with `replacement`, or with the tile type bordering it the
+    /// most if `None`. Rules run in the order they are added.
+    /// See: `GeneratedMap::feature_size_stats`
+    pub fn with_min_feature_size(
+        mut self,
+        visual_type: MapTileVisualType,
+        min_area: usize,
+        replacement: Option<MapTileVisualType>,
+    ) -> Self {
+        assert!(
+            replacement.as_ref() != Some(&visual_type),
+            "Replacement tile type needs to differ from the filtered tile type"
+        );
+
+        let rule = FeatureSizeRule {
+            visual_type,
+            min_area,
+            replacement,
+        };
+        self.record_step(
+            format_args!("min_feature_size={}", get_feature_size_encoding(&rule)),
+            format_args!("with_min_feature_size({:?})", rule),
+        );
+        self.feature_size_rules.push(rule);
+        self
+    }
+
+    /// On any walkable land tiles, place evenly spaced points of interest of the given kind, at
+    /// least `min_distance` tiles apart. Can be called once per kind of point of interest.
+    /// Building points are stamped into the map as single-tile buildings once every layer is placed.
+    /// See: `effigy_gen::points_of_interest::place_chunk_points_of_interest`
+    pub fn with_points_of_interest(
+        mut self,
+        kind: PointOfInterestKind,
+        min_distance: f64,
+        density: f64,
+    ) -> Self {
+        assert!(
+            density >= 0.0 && density <= 1.0,
+            "Density value needs to be between 0.0 and 1.0 (inclusive)"
+        );
+        assert!(
+            min_distance >= 1.0,
+            "Minimum distance value needs to be at least 1.0"
+        );
+        assert!(
+            !self.poi_layers.iter().any(|layer| layer.kind == kind),
+            "Points of interest of kind {:?} were already added",
+            kind
+        );
+
+        let layer = PointOfInterestLayer {
+            kind,
+            min_distance,
+            density,
+        };
+        self.record_step(
+            format_args!("points_of_interest={}", get_poi_layer_encoding(&layer)),
+            format_args!("with_points_of_interest({:?})", layer),
+        );
+        self.poi_layers.push(layer);
+        self
+    }
+
+    /// Stamp an authored prefab into the map when generating it, after the layers are placed and
+    /// buildings are shaped, replacing every tile under its cells other than "don't care" cells.
+    /// Prefabs are placed in the order they are added, and walkability around them is decided
+    /// like for the rest of the map. Connectivity, if enabled, may still open a way into them.
+    /// See: `PrefabPlacement`
+    pub fn with_prefab(mut self, prefab: Prefab, placement: PrefabPlacement) -> Self {
+        let width = self.map_grid.get_tiles_bounds().x as usize;
+        let height = self.map_grid.get_tiles_bounds().y as usize;
+        assert!(
+            prefab.get_width() <= width && prefab.get_height() <= height,
+            "Prefab of {}x{} tiles doesn't fit in the map",
+            prefab.get_width(),
+            prefab.get_height()
+        );
+        match placement {
+            PrefabPlacement::At { x, y } => assert!(
+                x as usize + prefab.get_width() <= width
+                    && y as usize + prefab.get_height() <= height,
+                "Prefab at ({}, {}) doesn't fit in the map",
+                x,
+                y
+            ),
+            PrefabPlacement::Scattered { min_distance, .. } => assert!(
+                min_distance >= 0.0,
+                "Minimum distance value needs to be at least 0.0"
+            ),
+        }
+
+        let layer = PrefabLayer { prefab, placement };
+        self.record_step(
+            format_args!("prefab={}", get_prefab_layer_encoding(&layer)),
+            format_args!("with_prefab({:?})", layer),
+        );
+        self.prefab_layers.push(layer);
+        self
+    }
+
+    /// Smooth the mask of every noise layer added after this (trees, water, terrain and buildings)
+    /// with a cellular automaton, removing single-tile specks and holes before the layer is placed.
+    /// See: `effigy_gen::smoothing::smooth_mask`
+    pub fn with_smoothing(mut self, rule: SmoothingRule) -> Self {
+        self.record_step(
+            format_args!("smoothing={}", get_smoothing_encoding(&rule)),
+            format_args!("with_smoothing({:?})", rule),
+        );
+        self.smoothing_rule = Some(rule);
+        self
+    }
+
+    /// Stop smoothing the masks of noise layers added after this
+    pub fn without_smoothing(mut self) -> Self {
+        self.record_step(
+            format_args!("without_smoothing"),
+            format_args!("without_smoothing()"),
+        );
+        self.smoothing_rule = None;
+        self
+    }
+
     /// On any land tiles, generate a map containing tree tiles
     pub fn with_trees(mut self, density: f64, scatter: f64) -> Self {
         assert!(
@@ -41,11 +363,16 @@ impl MapGenerator {
             std::time::Instant::now()
         };
 
+        self.record_step(
+            format_args!("trees {} {}", density, scatter),
+            format_args!("with_trees({}, {})", density, scatter),
+        );
+        self.has_layers = true;
         let seed = self.seed.wrapping_add(3453453666);
         let width = self.map_grid.get_tiles_bounds().x as usize;
         let height = self.map_grid.get_tiles_bounds().y as usize;
 
-        let mut noise_map = generate_foilage_noise(seed, width, height, scatter);
+        let mut noise_map = build_foilage_noise(seed, width, height, scatter, self.is_wrapping);
 
         info!(
             "... generated tree noise map in {:.3} seconds ...",
@@ -56,9 +383,10 @@ impl MapGenerator {
         noise_map.write_to_file("generated_trees.png");
 
         let noise_max = 0.0 - (0.5 + (0.5 * (1.0 - density)));
+        let mask = self.get_layer_mask(&noise_map, noise_max);
         for y in 0..height {
             for x in 0..width {
-                if noise_map.get_value(x, y) < noise_max {
+                if mask[y * width + x] {
                     noise_map.set_value(x, y, 1.0);
                     let tile = self.map_grid
                         .get_tile_mut(&MapTileXY {
@@ -103,6 +431,11 @@ impl MapGenerator {
             "Scatter value needs to be between 0.0 and 1.0 (inclusive)"
         );
 
+        self.record_step(
+            format_args!("water {} {}", density, scatter),
+            format_args!("with_water({}, {})", density, scatter),
+        );
+        self.has_layers = true;
         let seed = self.seed.wrapping_add(16654312);
         let width = self.map_grid.get_tiles_bounds().x as usize;
         let height = self.map_grid.get_tiles_bounds().y as usize;
@@ -115,7 +448,7 @@ impl MapGenerator {
             std::time::Instant::now()
         };
 
-        let mut noise_map = generate_water_noise(seed, width, height, scatter);
+        let mut noise_map = build_water_noise(seed, width, height, scatter, self.is_wrapping);
 
         info!(
             "... generated water noise map in {:.3} seconds ...",
@@ -126,9 +459,10 @@ impl MapGenerator {
         noise_map.write_to_file("generated_water.png");
 
         let noise_max = -1.0 + (0.8 * density);
+        let mask = self.get_layer_mask(&noise_map, noise_max);
         for y in 0..height {
             for x in 0..width {
-                if noise_map.get_value(x, y) < noise_max {
+                if mask[y * width + x] {
                     noise_map.set_value(x, y, 1.0);
                     let tile = self
                         .map_grid
@@ -168,6 +502,11 @@ impl MapGenerator {
             "Scatter value needs to be between 0.0 and 1.0 (inclusive)"
         );
 
+        self.record_step(
+            format_args!("terrain {} {}", density, scatter),
+            format_args!("with_terrain({}, {})", density, scatter),
+        );
+        self.has_layers = true;
         let seed = self.seed.wrapping_add(1443443);
         let width = self.map_grid.get_tiles_bounds().x as usize;
         let height = self.map_grid.get_tiles_bounds().y as usize;
@@ -180,7 +519,7 @@ impl MapGenerator {
             std::time::Instant::now()
         };
 
-        let mut noise_map = generate_landscape_noise(seed, width, height, scatter);
+        let mut noise_map = build_landscape_noise(seed, width, height, scatter, self.is_wrapping);
 
         info!(
             "... generated terrain noise map in {:.3} seconds ...",
@@ -191,9 +530,10 @@ impl MapGenerator {
         noise_map.write_to_file("generated_terrain.png");
 
         let noise_max = -1.0 + (0.8 * density);
+        let mask = self.get_layer_mask(&noise_map, noise_max);
         for y in 0..height {
             for x in 0..width {
-                if noise_map.get_value(x, y) < noise_max {
+                if mask[y * width + x] {
                     noise_map.set_value(x, y, 1.0);
                     let tile = self
                         .map_grid
@@ -223,7 +563,109 @@ impl MapGenerator {
         self
     }
 
-    pub fn with_buildings(mut self, density: f64, scale: f64) -> Self {
+    /// On any land or tree tiles, generate rivers which flow downhill into lakes or off the map.
+    /// See: `effigy_gen::river_gen::trace_river_tiles`
+    pub fn with_rivers(mut self, density: f64, width: u32) -> Self {
+        assert!(
+            density >= 0.0 && density <= 1.0,
+            "Density value needs to be between 0.0 and 1.0 (inclusive)"
+        );
+        assert!(width > 0, "Width value needs to be greater than 0");
+
+        self.record_step(
+            format_args!("rivers {} {}", density, width),
+            format_args!("with_rivers({}, {})", density, width),
+        );
+        self.has_layers = true;
+        let seed = self.seed.wrapping_add(5568321);
+        let width_tiles = self.map_grid.get_tiles_bounds().x as i64;
+        let height_tiles = self.map_grid.get_tiles_bounds().y as i64;
+
+        let start_time = {
+            info!(
+                "Populating map with rivers: density={} and width={} ...",
+                density, width
+            );
+            std::time::Instant::now()
+        };
+
+        let river_tiles = trace_river_tiles(
+            self.elevation_seed(),
+            seed,
+            density,
+            &self.map_grid.get_chunks_bounds(),
+            self.is_wrapping,
+        );
+
+        info!(
+            "... traced {} river tiles in {:.3} seconds ...",
+            river_tiles.len(),
+            start_time.elapsed().as_secs_f32()
+        );
+
+        #[cfg(feature = "images")]
+        let mut noise_map =
+            NoiseMap::new(width_tiles as usize, height_tiles as usize).set_border_value(-1.0);
+        #[cfg(feature = "images")]
+        noise_map.iter_mut().for_each(|value| *value = -1.0);
+
+        // Widen the center line of each river, biased towards the positive axes for even widths
+        let brush_min = -((width as i64 - 1) / 2);
+        let brush_max = (width as i64) / 2;
+        for (river_x, river_y) in river_tiles {
+            for y in (river_y + brush_min)..=(river_y + brush_max) {
+                for x in (river_x + brush_min)..=(river_x + brush_max) {
+                    let (x, y) = if self.is_wrapping {
+                        (x.rem_euclid(width_tiles), y.rem_euclid(height_tiles))
+                    } else {
+                        (x, y)
+                    };
+                    if x < 0 || y < 0 || x >= width_tiles || y >= height_tiles {
+                        continue;
+                    }
+                    let tile = self
+                        .map_grid
+                        .get_tile_mut(&MapTileXY {
+                            x: x as MapTileIndex,
+                            y: y as MapTileIndex,
+                        })
+                        .unwrap();
+                    // Rivers wash through trees, but flow around terrain and buildings
+                    if tile.visual_type == MapTileVisualType::Land
+                        || tile.visual_type == MapTileVisualType::LandTree
+                    {
+                        tile.set_visual_type(&MapTileVisualType::Water)
+                            .set_walkability_blocked(&SquareBounds::ALL);
+                        #[cfg(feature = "images")]
+                        noise_map.set_value(x as usize, y as usize, 1.0);
+                    }
+                }
+            }
+        }
+
+        #[cfg(feature = "images")]
+        noise_map.write_to_file("generated_rivers_mask.png");
+
+        info!(
+            "... done populating map with rivers! Completed in {:.3} seconds",
+            start_time.elapsed().as_secs_f32()
+        );
+        self
+    }
+
+    /// On any land tiles, generate a map containing building tiles
+    pub fn with_buildings(self, density: f64, scale: f64) -> Self {
+        self.with_styled_buildings(density, scale, &BuildingNoiseStyle::default())
+    }
+
+    /// On any land tiles, generate a map containing building tiles shaped by the given style.
+    /// See: `BuildingNoiseStyle`
+    pub fn with_styled_buildings(
+        mut self,
+        density: f64,
+        scale: f64,
+        style: &BuildingNoiseStyle,
+    ) -> Self {
         assert!(
             density >= 0.0 && density <= 1.0,
             "Density value needs to be between 0.0 and 1.0 (inclusive)"
@@ -232,17 +674,60 @@ impl MapGenerator {
             scale > 0.0 && scale <= 1.0,
             "Scale value needs to be between 0.0 (exclusive) and 1.0 (inclusive)"
         );
+        // Buildings are labeled as if the map were bounded, which would split those on the edges
+        assert!(!self.is_wrapping, "Wrap-around maps can't have buildings");
 
+        // Custom functions are told apart by id, as their addresses differ between builds
+        let distance_function = match style.distance_function {
+            BuildingDistanceFunction::Euclidean => "Euclidean".to_string(),
+            BuildingDistanceFunction::Manhattan => "Manhattan".to_string(),
+            BuildingDistanceFunction::Chebyshev => "Chebyshev".to_string(),
+            BuildingDistanceFunction::Custom { id, .. } => format!("Custom({})", id),
+        };
+        let return_type = match style.return_type {
+            ReturnType::Value => "Value",
+            ReturnType::Distance => "Distance",
+        };
+        self.record_step(
+            format_args!(
+                "buildings {} {} {} {}",
+                density, scale, distance_function, return_type
+            ),
+            format_args!(
+                "with_styled_buildings({}, {}, {}, {:?})",
+                density, scale, distance_function, style.return_type
+            ),
+        );
+        self.has_layers = true;
         let seed = self.seed.wrapping_add(2344443);
         let width = self.map_grid.get_tiles_bounds().x as usize;
         let height = self.map_grid.get_tiles_bounds().y as usize;
 
         let start_time = {
-            info!("Populating map with buildings: density={} ...", density);
+            info!(
+                "Populating map with buildings: density={} and style={:?} ...",
+                density, style
+            );
             std::time::Instant::now()
         };
 
-        let mut noise_map = generate_buildings_noise(seed, width, height, scale);
+        let mut noise_map =
+            generate_styled_buildings_noise(seed, width, height, scale, style, self.is_wrapping);
+        // Distance noise varies within a cell, so identify cells by the value of the same noise
+        let cell_noise_map = match style.return_type {
+            ReturnType::Value => None,
+            ReturnType::Distance => Some(generate_styled_buildings_noise(
+                seed,
+                width,
+                height,
+                scale,
+                &BuildingNoiseStyle {
+                    return_type: ReturnType::Value,
+                    ..*style
+                },
+                self.is_wrapping,
+            )),
+        };
 
         info!(
             "... generated buildings noise map in {:.3} seconds ...",
@@ -252,10 +737,19 @@ impl MapGenerator {
         #[cfg(feature = "images")]
         noise_map.write_to_file("generated_buildings.png");
 
-        let noise_max = -1.0 + (0.1 * density);
+        let noise_max = match style.return_type {
+            ReturnType::Value => -1.0 + (0.1 * density),
+            ReturnType::Distance => -1.0 + density,
+        };
+        let mask = self.get_layer_mask(&noise_map, noise_max);
         for y in 0..height {
             for x in 0..width {
-                if noise_map.get_value(x, y) < noise_max {
+                let value = noise_map.get_value(x, y);
+                if mask[y * width + x] {
+                    let cell_value = match &cell_noise_map {
+                        Some(cell_noise_map) => cell_noise_map.get_value(x, y),
+                        None => value,
+                    };
                     noise_map.set_value(x, y, 1.0);
                     let tile = self
                         .map_grid
@@ -267,6 +761,8 @@ impl MapGenerator {
                     if tile.visual_type == MapTileVisualType::Land {
                         tile.set_visual_type(&MapTileVisualType::Building)
                             .set_walkability_blocked(&SquareBounds::ALL);
+                        // Every tile of a Worley cell shares the same value
+                        self.building_cell_keys[y * width + x] = Some(cell_value.to_bits());
                     }
                 } else {
                     noise_map.set_value(x, y, -1.0);
@@ -284,132 +780,364 @@ impl MapGenerator {
         self
     }
 
+    /// Clean up the raw footprints of the building layer when generating the map, and give every
+    /// building a door. See: `BuildingShapeOptions`
+    pub fn with_building_shape(mut self, options: BuildingShapeOptions) -> Self {
+        assert!(options.doors >= 1, "Buildings need at least 1 door");
+        assert!(!self.is_wrapping, "Wrap-around maps can't have buildings");
+        self.record_step(
+            format_args!("building_shape={}", get_building_shape_encoding(&options)),
+            format_args!("with_building_shape({:?})", options),
+        );
+        self.building_shape_options = Some(options);
+        self
+    }
+
     /// Cleans up and returns the finalized map grid
-    pub fn generate(mut self) -> MapGrid {
+    pub fn generate(self) -> MapGrid {
+        self.generate_full().map_grid
+    }
+
+    /// Cleans up and returns the finalized map grid, along with any optional outputs
+    pub fn generate_full(mut self) -> GeneratedMap {
         let start_time = {
             info!("Generating final map ...");
             std::time::Instant::now()
         };
 
-        simplify_map_walkability_blockage(&mut self.map_grid);
+        if let Some(border) = &self.border {
+            let seed = self.seed.wrapping_add(9182736);
+            apply_map_border(&mut self.map_grid, seed, border);
+        }
+
+        let feature_size_stats: Vec<FeatureSizeStats> = self
+            .feature_size_rules
+            .iter()
+            .map(|rule| filter_small_features(&mut self.map_grid, rule))
+            .collect();
+
+        if let Some(options) = &self.building_shape_options {
+            shape_buildings(&mut self.map_grid, &self.building_cell_keys, options);
+        }
+
+        let prefab_sites = place_prefabs(
+            &mut self.map_grid,
+            &mut self.building_cell_keys,
+            self.seed.wrapping_add(4471029),
+            &self.prefab_layers,
+        );
 
-        // ...
+        if let Some(mode) = &self.connectivity_mode {
+            enforce_walkable_connectivity(&mut self.map_grid, mode, &self.walkability_rules);
+        }
+
+        let points_of_interest = self.place_points_of_interest();
+
+        simplify_map_walkability_blockage(
+            &mut self.map_grid,
+            &self.walkability_rules,
+            self.is_wrapping,
+        );
+
+        let mut buildings = label_buildings(&self.map_grid, &self.building_cell_keys);
+        separate_buildings(&mut self.map_grid, &buildings);
+        if let Some(options) = &self.building_shape_options {
+            let seed = self.seed.wrapping_add(6120931);
+            place_building_doors(&mut self.map_grid, &mut buildings, seed, options);
+        }
+
+        let heightmap = if self.heightmap_enabled {
+            Some(self.generate_heightmap())
+        } else {
+            None
+        };
 
         info!(
             "... done finalizing map! Completed in {:.3} seconds",
             start_time.elapsed().as_secs_f32()
         );
-        self.map_grid
+        GeneratedMap {
+            map_grid: self.map_grid,
+            heightmap,
+            points_of_interest,
+            buildings,
+            feature_size_stats,
+            prefab_sites,
+        }
     }
+
+    /// Generates the continuous elevation of every tile, shared by all elevation-aware layers
+    fn generate_elevation_noise(&self) -> NoiseMap {
+        let width = self.map_grid.get_tiles_bounds().x as usize;
+        let height = self.map_grid.get_tiles_bounds().y as usize;
+
+        build_elevation_noise(self.elevation_seed(), width, height, self.is_wrapping)
+    }
+
+    /// Tiles covered by a layer, where its noise is below `noise_max`, in row-major order
+    fn get_layer_mask(&self, noise_map: &NoiseMap, noise_max: f64) -> Vec<bool> {
+        let (width, height) = noise_map.size();
+        let mask: Vec<bool> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| noise_map.get_value(x, y) < noise_max)
+            .collect();
+        match &self.smoothing_rule {
+            Some(rule) => smooth_mask(&mask, width, height, rule, self.is_wrapping),
+            None => mask,
+        }
+    }
+
+    /// Mixes a builder step into the fingerprint, in the order the steps are called, and keeps its
+    /// description. The hashed encoding stays the same when descriptions change, so maps stored
+    /// against a fingerprint stay valid.
+    fn record_step(&mut self, encoding: std::fmt::Arguments, description: std::fmt::Arguments) {
+        self.fingerprint = hash_bytes(self.fingerprint, encoding.to_string().as_bytes());
+        self.steps.push(description.to_string());
+    }
+
+    fn elevation_seed(&self) -> u32 {
+        self.seed.wrapping_add(7734421)
+    }
+
+    /// Places the points of interest of every layer, then stamps building points into the map, so
+    /// every layer sees the same land and points of one layer don't depend on the others
+    fn place_points_of_interest(&mut self) -> Vec<PointOfInterest> {
+        let seed = get_points_of_interest_seed(self.seed);
+        let chunks_bounds = self.map_grid.get_chunks_bounds();
+
+        let width = self.map_grid.get_tiles_bounds().x as usize;
+        let mut points_of_interest: Vec<PointOfInterest> = Vec::new();
+        // Tiles taken by a point of an earlier layer, which takes precedence
+        let mut is_taken = vec![false; self.map_grid.get_tiles_count()];
+        for layer in &self.poi_layers {
+            let start_time = {
+                info!("Placing points of interest: {:?} ...", layer);
+                std::time::Instant::now()
+            };
+            let layer_start = points_of_interest.len();
+
+            for chunk_y in 0..chunks_bounds.y {
+                for chunk_x in 0..chunks_bounds.x {
+                    let chunk_points_of_interest = place_chunk_points_of_interest(
+                        &self.map_grid,
+                        seed,
+                        layer,
+                        &MapChunkXY {
+                            x: chunk_x,
+                            y: chunk_y,
+                        },
+                    );
+                    points_of_interest.extend(chunk_points_of_interest.into_iter().filter(|poi| {
+                        !is_taken[poi.tile.y as usize * width + poi.tile.x as usize]
+                    }));
+                }
+            }
+
+            for poi in &points_of_interest[layer_start..] {
+                is_taken[poi.tile.y as usize * width + poi.tile.x as usize] = true;
+            }
+            info!(
+                "... done placing {} points of interest! Completed in {:.3} seconds",
+                points_of_interest.len() - layer_start,
+                start_time.elapsed().as_secs_f32()
+            );
+        }
+
+        // Each building point is its own building, even when next to another one
+        for poi in &points_of_interest {
+            if poi.kind == PointOfInterestKind::Building {
+                let index = poi.tile.y as usize * width + poi.tile.x as usize;
+                get_tile_at_index_mut(&mut self.map_grid, width, index)
+                    .set_visual_type(&MapTileVisualType::Building);
+                self.building_cell_keys[index] =
+                    Some(hash_coords(seed, poi.tile.x as i64, poi.tile.y as i64));
+            }
+        }
+        points_of_interest
+    }
+
+    fn generate_heightmap(&self) -> MapHeightmap {
+        let start_time = {
+            info!("Generating heightmap ...");
+            std::time::Instant::now()
+        };
+
+        let noise_map = self.generate_elevation_noise();
+
+        #[cfg(feature = "images")]
+        noise_map.write_to_file("generated_elevation.png");
+
+        let heightmap = MapHeightmap::from_map_grid(&self.map_grid, &noise_map);
+
+        info!(
+            "... done generating heightmap! Completed in {:.3} seconds",
+            start_time.elapsed().as_secs_f32()
+        );
+        heightmap
+    }
+}
+
+// Fingerprint encodings of builder step parameters, written out field by field so the fingerprint
+// doesn't change with their `Debug` output
+
+fn get_optional_tile_char(visual_type: Option<&MapTileVisualType>) -> char {
+    visual_type.map_or('?', get_tile_char)
+}
+
+fn get_border_encoding(border: &MapBorder) -> String {
+    let style = match border.style {
+        BorderStyle::Ocean => "ocean",
+        BorderStyle::Terrain => "terrain",
+        BorderStyle::Wall => "wall",
+    };
+    let shape = match border.shape {
+        BorderShape::Square => "square",
+        BorderShape::Radial => "radial",
+    };
+    format!("{} {} {} {}", style, shape, border.width, border.noisiness)
+}
+
+fn get_connectivity_encoding(mode: &ConnectivityMode) -> String {
+    match mode {
+        ConnectivityMode::CarvePassages => "carve".to_string(),
+        ConnectivityMode::FillPockets { max_area } => format!("fill {}", max_area),
+    }
+}
+
+fn get_walkability_encoding(rules: &WalkabilityRules) -> String {
+    // Every ordered pair of tile types, as in the rule table
+    let mut encoding = String::new();
+    for from in VISUAL_TYPES.iter() {
+        for to in VISUAL_TYPES.iter() {
+            encoding.push(if rules.is_passable(from, to) {
+                '1'
+            } else {
+                '0'
+            });
+        }
+    }
+    encoding.push(' ');
+    encoding.push(if rules.are_outer_edges_blocked() {
+        '1'
+    } else {
+        '0'
+    });
+    encoding
 }
 
-fn simplify_map_walkability_blockage(map_grid: &mut MapGrid) {
+fn get_feature_size_encoding(rule: &FeatureSizeRule) -> String {
+    format!(
+        "{} {} {}",
+        get_tile_char(&rule.visual_type),
+        rule.min_area,
+        get_optional_tile_char(rule.replacement.as_ref())
+    )
+}
+
+fn get_poi_layer_encoding(layer: &PointOfInterestLayer) -> String {
+    format!(
+        "{} {} {}",
+        layer.kind as u8, layer.min_distance, layer.density
+    )
+}
+
+fn get_prefab_layer_encoding(layer: &PrefabLayer) -> String {
+    let prefab = &layer.prefab;
+    let mut encoding = format!("{}x{} ", prefab.get_width(), prefab.get_height());
+    for y in 0..prefab.get_height() {
+        for x in 0..prefab.get_width() {
+            encoding.push(get_optional_tile_char(prefab.get_cell(x, y)));
+        }
+    }
+    match layer.placement {
+        PrefabPlacement::At { x, y } => encoding += &format!(" at {} {}", x, y),
+        PrefabPlacement::Scattered {
+            count,
+            min_distance,
+        } => encoding += &format!(" scattered {} {}", count, min_distance),
+    }
+    encoding
+}
+
+fn get_smoothing_encoding(rule: &SmoothingRule) -> String {
+    let get_table_encoding = |table: &[bool; 9]| -> String {
+        table
+            .iter()
+            .map(|is_set| if *is_set { '1' } else { '0' })
+            .collect()
+    };
+    format!(
+        "{} {} {}",
+        get_table_encoding(&rule.birth),
+        get_table_encoding(&rule.survival),
+        rule.iterations
+    )
+}
+
+fn get_building_shape_encoding(options: &BuildingShapeOptions) -> String {
+    format!(
+        "{} {} {}",
+        options.min_area, options.rectangular, options.doors
+    )
+}
+
+/// Sets every side of every tile as blocked or not from the tile types on either side of the edge,
+/// as decided by the rules, replacing the blockage set while placing the layers.
+///
+/// Both sides of every edge are set, as units only need the side of the tile they're leaving to
+/// be unblocked. See: `MapTile::walkability_blocked`
+pub(crate) fn simplify_map_walkability_blockage(
+    map_grid: &mut MapGrid,
+    rules: &WalkabilityRules,
+    is_wrapping: bool,
+) {
     info!("Simplifying walkability blockage between tiles ...");
     let func_start_time = std::time::Instant::now();
 
     let tile_width = map_grid.get_tiles_bounds().x as usize;
     let tile_height = map_grid.get_tiles_bounds().y as usize;
+    // Neighboring row or column, which is across the map from the outer edges when wrapping
+    let get_neighbor = |value: usize, length: usize, is_forward: bool| -> Option<usize> {
+        match (is_forward, is_wrapping) {
+            (true, true) => Some((value + 1) % length),
+            (true, false) => (value < length - 1).then(|| value + 1),
+            (false, true) => Some((value + length - 1) % length),
+            (false, false) => value.checked_sub(1),
+        }
+    };
 
     for y in 0..tile_height {
         for x in 0..tile_width {
-            let mut current_tile_copy = map_grid
-                .get_tile(&MapTileXY {
-                    x: x as MapTileIndex,
-                    y: y as MapTileIndex,
-                })
-                .unwrap()
-                .clone();
-            // Check tile (x - 1)
-            if x > 0 {
-                // If the same tile type to the left shares a border, remove the blockage
-                if current_tile_copy.walkability_blocked.left {
-                    let left_tile = map_grid
-                        .get_tile_mut(&MapTileXY {
-                            x: (x - 1) as MapTileIndex,
-                            y: y as MapTileIndex,
-                        })
-                        .unwrap();
-                    if left_tile.visual_type == current_tile_copy.visual_type
-                        && left_tile.walkability_blocked.right
-                    {
-                        current_tile_copy.walkability_blocked.left = false;
-                        left_tile.walkability_blocked.right = false;
+            let tile_xy = MapTileXY {
+                x: x as MapTileIndex,
+                y: y as MapTileIndex,
+            };
+            let visual_type = &map_grid.get_tile(&tile_xy).unwrap().visual_type;
+            // A side is blocked if units can't step from this tile into the tile across it
+            let is_side_blocked = |neighbor_xy: Option<(usize, usize)>| -> bool {
+                match neighbor_xy {
+                    Some((neighbor_x, neighbor_y)) => {
+                        let neighbor_tile = map_grid
+                            .get_tile(&MapTileXY {
+                                x: neighbor_x as MapTileIndex,
+                                y: neighbor_y as MapTileIndex,
+                            })
+                            .unwrap();
+                        !rules.is_passable(visual_type, &neighbor_tile.visual_type)
                     }
+                    None => rules.are_outer_edges_blocked(),
                 }
-            } else {
-                // Left edge of map is always blocked
-                current_tile_copy.walkability_blocked.left = true;
-            }
-            // Check tile (y - 1)
-            if y > 0 {
-                // If the same tile type downwards shares a border, remove the blockage
-                if current_tile_copy.walkability_blocked.down {
-                    let down_tile = map_grid
-                        .get_tile_mut(&MapTileXY {
-                            x: x as MapTileIndex,
-                            y: (y - 1) as MapTileIndex,
-                        })
-                        .unwrap();
-                    if down_tile.visual_type == current_tile_copy.visual_type
-                        && down_tile.walkability_blocked.up
-                    {
-                        current_tile_copy.walkability_blocked.down = false;
-                        down_tile.walkability_blocked.up = false;
-                    }
-                }
-            } else {
-                // Bottom edge of map is always blocked
-                current_tile_copy.walkability_blocked.down = true;
-            }
-            // Check tile (width - 1)
-            if x < tile_width - 1 {
-                // If the same tile type to the right shares a border, remove the blockage
-                if current_tile_copy.walkability_blocked.right {
-                    let right_tile = map_grid
-                        .get_tile_mut(&MapTileXY {
-                            x: (x + 1) as MapTileIndex,
-                            y: y as MapTileIndex,
-                        })
-                        .unwrap();
-                    if right_tile.visual_type == current_tile_copy.visual_type
-                        && right_tile.walkability_blocked.left
-                    {
-                        current_tile_copy.walkability_blocked.right = false;
-                        right_tile.walkability_blocked.left = false;
-                    }
-                }
-            } else {
-                // Right edge of map is always blocked
-                current_tile_copy.walkability_blocked.right = true;
-            }
-            // Check tile (height - 1)
-            if y < tile_height - 1 {
-                // If the same tile type upwards shares a border, remove the blockage
-                if current_tile_copy.walkability_blocked.up {
-                    let up_tile = map_grid
-                        .get_tile_mut(&MapTileXY {
-                            x: x as MapTileIndex,
-                            y: (y + 1) as MapTileIndex,
-                        })
-                        .unwrap();
-                    if up_tile.visual_type == current_tile_copy.visual_type
-                        && up_tile.walkability_blocked.down
-                    {
-                        current_tile_copy.walkability_blocked.up = false;
-                        up_tile.walkability_blocked.down = false;
-                    }
-                }
-            } else {
-                // Top edge of map is always blocked
-                current_tile_copy.walkability_blocked.up = true;
-            }
+            };
+            let walkability_blocked = SquareBounds {
+                left: is_side_blocked(get_neighbor(x, tile_width, false).map(|x| (x, y))),
+                right: is_side_blocked(get_neighbor(x, tile_width, true).map(|x| (x, y))),
+                up: is_side_blocked(get_neighbor(y, tile_height, true).map(|y| (x, y))),
+                down: is_side_blocked(get_neighbor(y, tile_height, false).map(|y| (x, y))),
+            };
             map_grid
-                .get_tile_mut(&MapTileXY {
-                    x: x as MapTileIndex,
-                    y: y as MapTileIndex,
-                })
+                .get_tile_mut(&tile_xy)
                 .unwrap()
-                .set_walkability_blocked(&current_tile_copy.walkability_blocked);
+                .set_walkability_blocked(&walkability_blocked);
         }
     }
 
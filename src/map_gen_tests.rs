@@ -1,11 +1,22 @@
 #[allow(unused_imports)]
 use effigy_shared::logging::{debug, error, info, trace, warn};
 
-use effigy_shared::game::map::{MapChunkXY, MapGrid, MAP_CHUNK_TILES_LENGTH};
+use noise::utils::NoiseMap;
 
+use effigy_shared::game::map::{
+    MapChunkXY, MapGrid, MapTileIndex, MapTileVisualType, MapTileXY, MAP_CHUNK_TILES_LENGTH,
+};
+
+use super::connectivity::ConnectivityMode;
 #[cfg(feature = "images")]
 use super::images::map_as_image::save_map_grid_as_image;
-use super::map_gen::MapGenerator;
+use super::map_gen::{simplify_map_walkability_blockage, MapGenerator};
+use super::noise_gen::{
+    build_elevation_noise, build_foilage_noise, build_landscape_noise, build_water_noise,
+};
+use super::regions::{label_map_regions, RegionConnectivity};
+use super::smoothing::SmoothingRule;
+use super::walkability::{EdgeRule, WalkabilityRules};
 
 #[test]
 fn generate_example_tile_grid() {
@@ -40,3 +51,280 @@ fn generate_example_tile_grid() {
     #[cfg(feature = "images")]
     save_map_grid_as_image(&grid, &format!("map_gen_{}.png", seed)).unwrap();
 }
+
+#[test]
+fn generate_heightmap_orders_tile_types() {
+    let seed: u32 = 453537;
+    let map_chunks_size = MapChunkXY { x: 2, y: 2 };
+    let generate = || {
+        MapGenerator::new(seed, &map_chunks_size)
+            .with_terrain(0.2, 0.5)
+            .with_water(0.2, 0.5)
+            .with_heightmap()
+            .generate_full()
+    };
+    let generated = generate();
+    let heightmap = generated
+        .heightmap
+        .expect("Expected a heightmap to be generated");
+
+    let mut max_water_height: f32 = 0.0;
+    let mut min_terrain_height: f32 = 1.0;
+    for y in 0..generated.map_grid.get_tiles_bounds().y {
+        for x in 0..generated.map_grid.get_tiles_bounds().x {
+            let tile_xy = MapTileXY { x, y };
+            let height = heightmap.get_height(&tile_xy).unwrap();
+            assert!((0.0..=1.0).contains(&height));
+            match generated.map_grid.get_tile(&tile_xy).unwrap().visual_type {
+                MapTileVisualType::Water => max_water_height = max_water_height.max(height),
+                MapTileVisualType::Terrain => min_terrain_height = min_terrain_height.min(height),
+                _ => {}
+            }
+        }
+    }
+    assert!(max_water_height < min_terrain_height);
+
+    // The same seed and parameters must always produce the same heights
+    let chunk_xy = MapChunkXY { x: 1, y: 0 };
+    let chunk_heights = heightmap.get_chunk_heights(&chunk_xy).unwrap();
+    assert_eq!(
+        chunk_heights.len(),
+        (MAP_CHUNK_TILES_LENGTH as usize) * (MAP_CHUNK_TILES_LENGTH as usize)
+    );
+    assert_eq!(
+        chunk_heights,
+        generate()
+            .heightmap
+            .unwrap()
+            .get_chunk_heights(&chunk_xy)
+            .unwrap()
+    );
+    assert_eq!(
+        chunk_heights[0],
+        heightmap
+            .get_height(&MapTileXY {
+                x: MAP_CHUNK_TILES_LENGTH as MapTileIndex,
+                y: 0
+            })
+            .unwrap()
+    );
+}
+
+#[test]
+fn generate_rivers_independent_of_map_size() {
+    let seed: u32 = 453537;
+    let small_grid = MapGenerator::new(seed, &MapChunkXY { x: 2, y: 2 })
+        .with_rivers(0.8, 2)
+        .generate();
+    let large_grid = MapGenerator::new(seed, &MapChunkXY { x: 3, y: 3 })
+        .with_rivers(0.8, 2)
+        .generate();
+
+    let mut river_tiles_count: usize = 0;
+    for y in 0..small_grid.get_tiles_bounds().y {
+        for x in 0..small_grid.get_tiles_bounds().x {
+            let tile_xy = MapTileXY { x, y };
+            let small_visual_type = &small_grid.get_tile(&tile_xy).unwrap().visual_type;
+            assert_eq!(
+                small_visual_type,
+                &large_grid.get_tile(&tile_xy).unwrap().visual_type,
+                "Expected river tiles to match at ({}, {})",
+                x,
+                y
+            );
+            if *small_visual_type == MapTileVisualType::Water {
+                river_tiles_count += 1;
+            }
+        }
+    }
+    assert!(river_tiles_count > 0, "Expected at least one river tile");
+}
+
+/// Counts the 4-connected regions of land tiles
+fn count_land_regions(grid: &MapGrid) -> usize {
+    label_map_regions(grid, RegionConnectivity::VisualType)
+        .get_regions()
+        .iter()
+        .filter(|region| region.visual_type == MapTileVisualType::Land)
+        .count()
+}
+
+#[test]
+fn generate_with_connectivity() {
+    let seed: u32 = 453537;
+    let map_chunks_size = MapChunkXY { x: 2, y: 2 };
+    let generator = || {
+        MapGenerator::new(seed, &map_chunks_size)
+            .with_terrain(0.4, 0.5)
+            .with_trees(0.4, 0.5)
+    };
+    assert!(count_land_regions(&generator().generate()) > 1);
+
+    let carved_grid = generator()
+        .with_connectivity(ConnectivityMode::CarvePassages)
+        .generate();
+    assert_eq!(count_land_regions(&carved_grid), 1);
+
+    let filled_grid = generator()
+        .with_connectivity(ConnectivityMode::FillPockets {
+            max_area: usize::MAX,
+        })
+        .generate();
+    assert_eq!(count_land_regions(&filled_grid), 1);
+}
+
+#[test]
+fn generate_with_connectivity_under_walkability_rules() {
+    let seed: u32 = 453537;
+    let map_chunks_size = MapChunkXY { x: 2, y: 2 };
+    // Trees are walkable, so they join land into regions rather than split it
+    let rules = || {
+        WalkabilityRules::new()
+            .with_edge(
+                &MapTileVisualType::LandTree,
+                &MapTileVisualType::Land,
+                EdgeRule::Open,
+            )
+            .with_edge(
+                &MapTileVisualType::LandTree,
+                &MapTileVisualType::LandTree,
+                EdgeRule::Open,
+            )
+    };
+    let count_walkable_regions = |grid: &MapGrid| {
+        label_map_regions(grid, RegionConnectivity::Walkability)
+            .get_regions()
+            .iter()
+            .filter(|region| {
+                region.visual_type == MapTileVisualType::Land
+                    || region.visual_type == MapTileVisualType::LandTree
+            })
+            .count()
+    };
+    let generator = |terrain_frequency: f64| {
+        MapGenerator::new(seed, &map_chunks_size)
+            .with_terrain(terrain_frequency, 0.5)
+            .with_trees(0.4, 0.5)
+            .with_walkability_rules(rules())
+    };
+
+    let isolated_grid = generator(0.3).generate();
+    assert!(count_walkable_regions(&isolated_grid) > 1);
+    for mode in [
+        ConnectivityMode::CarvePassages,
+        ConnectivityMode::FillPockets {
+            max_area: usize::MAX,
+        },
+    ] {
+        let grid = generator(0.3).with_connectivity(mode).generate();
+        assert_eq!(count_walkable_regions(&grid), 1);
+    }
+
+    // Land split only by trees is already connected, so there's nothing to carve
+    let connected_grid = generator(0.4).generate();
+    assert!(count_land_regions(&connected_grid) > 1);
+    assert_eq!(count_walkable_regions(&connected_grid), 1);
+    let carved_grid = generator(0.4)
+        .with_connectivity(ConnectivityMode::CarvePassages)
+        .generate();
+    let bounds = connected_grid.get_tiles_bounds();
+    for y in 0..bounds.y {
+        for x in 0..bounds.x {
+            let tile_xy = MapTileXY { x, y };
+            assert_eq!(
+                carved_grid.get_tile(&tile_xy).unwrap().visual_type,
+                connected_grid.get_tile(&tile_xy).unwrap().visual_type,
+                "Expected no tiles to be carved"
+            );
+        }
+    }
+}
+
+/// Asserts that the first and last columns, and the first and last rows, of the noise differ no
+/// more than the most different neighboring columns and rows within it, as if they were neighbors
+fn assert_noise_tiles(noise_map: &NoiseMap) {
+    let (width, height) = noise_map.size();
+    let get_difference = |a: (usize, usize), b: (usize, usize), step: (usize, usize), count| {
+        (0..count)
+            .map(|i| {
+                (noise_map.get_value(a.0 + step.0 * i, a.1 + step.1 * i)
+                    - noise_map.get_value(b.0 + step.0 * i, b.1 + step.1 * i))
+                .abs()
+            })
+            .sum::<f64>()
+            / count as f64
+    };
+    let max_column_difference = (0..(width - 1))
+        .map(|x| get_difference((x, 0), (x + 1, 0), (0, 1), height))
+        .fold(0.0, f64::max);
+    assert!(get_difference((width - 1, 0), (0, 0), (0, 1), height) <= max_column_difference);
+    let max_row_difference = (0..(height - 1))
+        .map(|y| get_difference((0, y), (0, y + 1), (1, 0), width))
+        .fold(0.0, f64::max);
+    assert!(get_difference((0, height - 1), (0, 0), (1, 0), width) <= max_row_difference);
+}
+
+#[test]
+fn generate_with_wrap_around() {
+    // The noise of every layer continues across the map edges
+    let (width, height) = (
+        4 * MAP_CHUNK_TILES_LENGTH as usize,
+        4 * MAP_CHUNK_TILES_LENGTH as usize,
+    );
+    assert_noise_tiles(&build_landscape_noise(453537, width, height, 0.3, true));
+    assert_noise_tiles(&build_water_noise(453537, width, height, 0.3, true));
+    assert_noise_tiles(&build_foilage_noise(453537, width, height, 0.3, true));
+    assert_noise_tiles(&build_elevation_noise(453537, width, height, true));
+
+    let map_chunks_size = MapChunkXY { x: 4, y: 4 };
+    let grid = MapGenerator::new(453537, &map_chunks_size)
+        .with_wrap_around()
+        .with_smoothing(SmoothingRule::four_five(1))
+        .with_terrain(0.3, 0.5)
+        .with_water(0.3, 0.5)
+        .with_trees(0.3, 0.5)
+        .with_rivers(0.2, 2)
+        .generate();
+    let width = grid.get_tiles_bounds().x;
+    let height = grid.get_tiles_bounds().y;
+
+    // Repeating the map 3 by 3 and deciding walkability as if it were bounded gives the middle copy
+    // the same neighbors across its edges as the wrapped map has across its seams
+    let mut tiled = MapGrid::new(&MapChunkXY {
+        x: map_chunks_size.x * 3,
+        y: map_chunks_size.y * 3,
+    });
+    for y in 0..height * 3 {
+        for x in 0..width * 3 {
+            let visual_type = &grid
+                .get_tile(&MapTileXY {
+                    x: x % width,
+                    y: y % height,
+                })
+                .unwrap()
+                .visual_type;
+            tiled
+                .get_tile_mut(&MapTileXY { x, y })
+                .unwrap()
+                .set_visual_type(visual_type);
+        }
+    }
+    simplify_map_walkability_blockage(&mut tiled, &WalkabilityRules::default(), false);
+
+    for y in 0..height {
+        for x in 0..width {
+            let tile = grid.get_tile(&MapTileXY { x, y }).unwrap();
+            let tiled_tile = tiled
+                .get_tile(&MapTileXY {
+                    x: x + width,
+                    y: y + height,
+                })
+                .unwrap();
+            assert_eq!(
+                tile.walkability_blocked, tiled_tile.walkability_blocked,
+                "Expected matching walkability at {}, {}",
+                x, y
+            );
+        }
+    }
+}
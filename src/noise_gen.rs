@@ -1,14 +1,85 @@
 #[allow(unused_imports)]
 use effigy_shared::logging::{debug, error, info, trace, warn};
 
-use noise::core::worley::distance_functions::euclidean;
+use noise::core::worley::distance_functions::{chebyshev, euclidean, manhattan};
 use noise::core::worley::ReturnType;
 use noise::utils::{NoiseMap, NoiseMapBuilder, PlaneMapBuilder};
-use noise::{MultiFractal, Perlin, RidgedMulti, Seedable, Worley};
+use noise::{Fbm, MultiFractal, NoiseFn, Perlin, RidgedMulti, Seedable, Worley};
 
 use effigy_shared::game::map::MAP_CHUNK_TILES_LENGTH;
 
+/// Samples a noise function over a map of `width` by `height` tiles within the given bounds.
+///
+/// When seamless, each axis is wrapped around a circle with the same circumference as its bounds,
+/// sampling a torus in 4D noise space, so the map tiles perfectly when repeated. Unlike the
+/// blending of `PlaneMapBuilder::set_is_seamless`, this keeps the character of the noise, such as
+/// every Worley cell having a single value.
+fn build_noise_map<F>(
+    noise_fn: F,
+    width: usize,
+    height: usize,
+    x_bounds: (f64, f64),
+    y_bounds: (f64, f64),
+    is_seamless: bool,
+) -> NoiseMap
+where
+    F: NoiseFn<f64, 2> + NoiseFn<f64, 4>,
+{
+    if !is_seamless {
+        return PlaneMapBuilder::<_, 2>::new(noise_fn)
+            .set_size(width, height)
+            .set_x_bounds(x_bounds.0, x_bounds.1)
+            .set_y_bounds(y_bounds.0, y_bounds.1)
+            .set_is_seamless(false)
+            .build();
+    }
+
+    let mut noise_map = NoiseMap::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let point = get_seamless_point(x, y, width, height, x_bounds, y_bounds);
+            noise_map.set_value(x, y, NoiseFn::<f64, 4>::get(&noise_fn, point));
+        }
+    }
+    noise_map
+}
+
+/// The 4D point sampled for a tile of a seamless noise map. See: `build_noise_map`
+pub(crate) fn get_seamless_point(
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    x_bounds: (f64, f64),
+    y_bounds: (f64, f64),
+) -> [f64; 4] {
+    let x_radius = (x_bounds.1 - x_bounds.0) / std::f64::consts::TAU;
+    let y_radius = (y_bounds.1 - y_bounds.0) / std::f64::consts::TAU;
+    let x_angle = std::f64::consts::TAU * (x as f64) / (width as f64);
+    let y_angle = std::f64::consts::TAU * (y as f64) / (height as f64);
+    // Each axis is a circle starting from its lower bound
+    let x_center = x_bounds.0 + x_radius;
+    let y_center = y_bounds.0 + y_radius;
+    [
+        x_center - x_radius * x_angle.cos(),
+        x_center + x_radius * x_angle.sin(),
+        y_center - y_radius * y_angle.cos(),
+        y_center + y_radius * y_angle.sin(),
+    ]
+}
+
 pub fn generate_foilage_noise(seed: u32, width: usize, height: usize, scatter: f64) -> NoiseMap {
+    build_foilage_noise(seed, width, height, scatter, false)
+}
+
+/// Foilage noise, optionally seamless. See: `build_noise_map`
+pub(crate) fn build_foilage_noise(
+    seed: u32,
+    width: usize,
+    height: usize,
+    scatter: f64,
+    is_seamless: bool,
+) -> NoiseMap {
     assert!(
         scatter >= 0.0 && scatter <= 1.0,
         "Scatter value needs to be between 0.0 and 1.0 (inclusive)"
@@ -25,17 +96,28 @@ pub fn generate_foilage_noise(seed: u32, width: usize, height: usize, scatter: f
         .set_seed(seed)
         .set_frequency(80.0 * scatter);
 
-    let noise_map = PlaneMapBuilder::<_, 2>::new(ridged_multi_perlin)
-        .set_size(width, height)
-        .set_x_bounds(-xy_radius, xy_radius)
-        .set_y_bounds(-xy_radius, xy_radius)
-        .set_is_seamless(false)
-        .build();
-
-    noise_map
+    build_noise_map(
+        ridged_multi_perlin,
+        width,
+        height,
+        (-xy_radius, xy_radius),
+        (-xy_radius, xy_radius),
+        is_seamless,
+    )
 }
 
 pub fn generate_water_noise(seed: u32, width: usize, height: usize, scatter: f64) -> NoiseMap {
+    build_water_noise(seed, width, height, scatter, false)
+}
+
+/// Water noise, optionally seamless. See: `build_noise_map`
+pub(crate) fn build_water_noise(
+    seed: u32,
+    width: usize,
+    height: usize,
+    scatter: f64,
+    is_seamless: bool,
+) -> NoiseMap {
     assert!(
         scatter >= 0.0 && scatter <= 1.0,
         "Scatter value needs to be between 0.0 and 1.0 (inclusive)"
@@ -52,17 +134,28 @@ pub fn generate_water_noise(seed: u32, width: usize, height: usize, scatter: f64
         .set_seed(seed)
         .set_frequency(20.0 * scatter);
 
-    let noise_map = PlaneMapBuilder::<_, 2>::new(ridged_multi_perlin)
-        .set_size(width, height)
-        .set_x_bounds(-xy_radius, xy_radius)
-        .set_y_bounds(-xy_radius, xy_radius)
-        .set_is_seamless(false)
-        .build();
-
-    noise_map
+    build_noise_map(
+        ridged_multi_perlin,
+        width,
+        height,
+        (-xy_radius, xy_radius),
+        (-xy_radius, xy_radius),
+        is_seamless,
+    )
 }
 
 pub fn generate_landscape_noise(seed: u32, width: usize, height: usize, scatter: f64) -> NoiseMap {
+    build_landscape_noise(seed, width, height, scatter, false)
+}
+
+/// Landscape noise, optionally seamless. See: `build_noise_map`
+pub(crate) fn build_landscape_noise(
+    seed: u32,
+    width: usize,
+    height: usize,
+    scatter: f64,
+    is_seamless: bool,
+) -> NoiseMap {
     assert!(
         scatter >= 0.0 && scatter <= 1.0,
         "Scatter value needs to be between 0.0 and 1.0 (inclusive)"
@@ -79,17 +172,106 @@ pub fn generate_landscape_noise(seed: u32, width: usize, height: usize, scatter:
         .set_seed(seed)
         .set_frequency(2.8 * scatter);
 
-    let noise_map = PlaneMapBuilder::<_, 2>::new(ridged_multi_perlin)
-        .set_size(width, height)
-        .set_x_bounds(-xy_radius, xy_radius)
-        .set_y_bounds(-xy_radius, xy_radius)
-        .set_is_seamless(false)
-        .build();
+    build_noise_map(
+        ridged_multi_perlin,
+        width,
+        height,
+        (-xy_radius, xy_radius),
+        (-xy_radius, xy_radius),
+        is_seamless,
+    )
+}
 
-    noise_map
+/// Distance function of the Worley noise used for building shapes
+#[derive(Clone, Copy, Debug)]
+pub enum BuildingDistanceFunction {
+    Euclidean,
+    Manhattan,
+    Chebyshev,
+    Custom {
+        /// Stable name of the function, which stands in for it in `MapGenerator::get_fingerprint`,
+        /// so different functions need different ids
+        id: &'static str,
+        distance_function: fn(&[f64], &[f64]) -> f64,
+    },
+}
+impl BuildingDistanceFunction {
+    fn get_fn(&self) -> fn(&[f64], &[f64]) -> f64 {
+        match self {
+            BuildingDistanceFunction::Euclidean => euclidean,
+            BuildingDistanceFunction::Manhattan => manhattan,
+            BuildingDistanceFunction::Chebyshev => chebyshev,
+            BuildingDistanceFunction::Custom {
+                distance_function, ..
+            } => *distance_function,
+        }
+    }
+}
+
+/// Shape of the Worley noise used for the building layer.
+///
+/// With `ReturnType::Value`, every Worley cell has a single random value, so a building is a whole
+/// cell and the distance function decides the outline of the cells. With `ReturnType::Distance`,
+/// the value grows with the distance from each cell's seed point, so a building is a blob around
+/// the seed point shaped like the distance function's unit circle.
+#[derive(Clone, Copy, Debug)]
+pub struct BuildingNoiseStyle {
+    pub distance_function: BuildingDistanceFunction,
+    pub return_type: ReturnType,
+}
+impl BuildingNoiseStyle {
+    /// Irregular convex polygons of varying size, like a mix of houses and halls
+    pub const POLYGONAL_CELLS: BuildingNoiseStyle = BuildingNoiseStyle {
+        distance_function: BuildingDistanceFunction::Euclidean,
+        return_type: ReturnType::Value,
+    };
+    /// Cells with long straight edges at right angles and 45 degrees, like city blocks
+    pub const URBAN_BLOCKS: BuildingNoiseStyle = BuildingNoiseStyle {
+        distance_function: BuildingDistanceFunction::Chebyshev,
+        return_type: ReturnType::Value,
+    };
+    /// Cells with diagonal edges, giving staggered and stepped outlines
+    pub const MANHATTAN_CELLS: BuildingNoiseStyle = BuildingNoiseStyle {
+        distance_function: BuildingDistanceFunction::Manhattan,
+        return_type: ReturnType::Value,
+    };
+    /// Small round blobs spread evenly across the map, like huts in a village
+    pub const ROUNDED_HUTS: BuildingNoiseStyle = BuildingNoiseStyle {
+        distance_function: BuildingDistanceFunction::Euclidean,
+        return_type: ReturnType::Distance,
+    };
+    /// Small axis-aligned squares spread evenly across the map, like tents in a camp
+    pub const SQUARE_HUTS: BuildingNoiseStyle = BuildingNoiseStyle {
+        distance_function: BuildingDistanceFunction::Chebyshev,
+        return_type: ReturnType::Distance,
+    };
+}
+impl Default for BuildingNoiseStyle {
+    fn default() -> Self {
+        BuildingNoiseStyle::POLYGONAL_CELLS
+    }
 }
 
+/// Building noise of the default style. See: `generate_styled_buildings_noise`
 pub fn generate_buildings_noise(seed: u32, width: usize, height: usize, scale: f64) -> NoiseMap {
+    generate_styled_buildings_noise(
+        seed,
+        width,
+        height,
+        scale,
+        &BuildingNoiseStyle::default(),
+        false,
+    )
+}
+
+pub fn generate_styled_buildings_noise(
+    seed: u32,
+    width: usize,
+    height: usize,
+    scale: f64,
+    style: &BuildingNoiseStyle,
+    is_seamless: bool,
+) -> NoiseMap {
     assert!(scale > 0.0, "Scale value needs to be greater than zero");
     assert!(
         width > 0 && width % (MAP_CHUNK_TILES_LENGTH as usize) == 0,
@@ -99,17 +281,64 @@ pub fn generate_buildings_noise(seed: u32, width: usize, height: usize, scale: f
     let chunks_width: usize = width / (MAP_CHUNK_TILES_LENGTH as usize);
     let xy_radius: f64 = (1.0 / scale) * (chunks_width as f64);
 
-    let worley_manhattan = Worley::default()
+    let worley = Worley::default()
         .set_seed(seed)
-        .set_distance_function(euclidean)
-        .set_return_type(ReturnType::Value);
+        .set_distance_function(style.distance_function.get_fn())
+        .set_return_type(style.return_type);
 
-    let noise_map = PlaneMapBuilder::<_, 2>::new(worley_manhattan)
-        .set_size(width, height)
-        .set_x_bounds(-xy_radius, xy_radius)
-        .set_y_bounds(-xy_radius, xy_radius)
-        .set_is_seamless(false)
-        .build();
+    build_noise_map(
+        worley,
+        width,
+        height,
+        (-xy_radius, xy_radius),
+        (-xy_radius, xy_radius),
+        is_seamless,
+    )
+}
 
-    noise_map
+/// Generates a smooth elevation field sampled at world tile coordinates.
+///
+/// Unlike the other layers, the sample bounds are anchored at the origin with one unit per chunk,
+/// so the value at any tile only depends on the seed and its world position, not the map size.
+pub fn generate_elevation_noise(seed: u32, width: usize, height: usize) -> NoiseMap {
+    build_elevation_noise(seed, width, height, false)
+}
+
+/// Elevation noise, optionally seamless. Seamless elevation wraps around the map instead of being
+/// anchored at the origin, so it depends on the map size. See: `generate_elevation_noise`
+pub(crate) fn build_elevation_noise(
+    seed: u32,
+    width: usize,
+    height: usize,
+    is_seamless: bool,
+) -> NoiseMap {
+    assert!(
+        width > 0 && width % (MAP_CHUNK_TILES_LENGTH as usize) == 0,
+        "Width of map needs to be greater than 0 and be a multiple of the chunk side length"
+    );
+    assert!(
+        height > 0 && height % (MAP_CHUNK_TILES_LENGTH as usize) == 0,
+        "Height of map needs to be greater than 0 and be a multiple of the chunk side length"
+    );
+
+    let chunks_width: usize = width / (MAP_CHUNK_TILES_LENGTH as usize);
+    let chunks_height: usize = height / (MAP_CHUNK_TILES_LENGTH as usize);
+
+    build_noise_map(
+        elevation_noise_fn(seed),
+        width,
+        height,
+        (0.0, chunks_width as f64),
+        (0.0, chunks_height as f64),
+        is_seamless,
+    )
+}
+
+/// The noise function behind `generate_elevation_noise`, for sampling elevation at arbitrary
+/// world positions (one unit per chunk) outside of the map bounds
+pub fn elevation_noise_fn(seed: u32) -> Fbm<Perlin> {
+    Fbm::<Perlin>::new(seed)
+        .set_frequency(0.35)
+        .set_persistence(0.5)
+        .set_octaves(4)
 }
@@ -0,0 +1,201 @@
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use effigy_shared::game::map::{MapGrid, MapTileVisualType, MapTileXY};
+
+use super::grid_index::{
+    get_neighbor_edges, get_tile_at_index, get_tile_xy_at_index, is_edge_walkable,
+};
+
+/// Cost of entering a tile, per `MapTileVisualType`. A cost of `None` makes the type impassable,
+/// even where its edges are walkable.
+///
+/// Costs are integers so paths are identical on every platform.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MovementCosts {
+    land: Option<u32>,
+    land_tree: Option<u32>,
+    water: Option<u32>,
+    terrain: Option<u32>,
+    building: Option<u32>,
+}
+impl MovementCosts {
+    /// Every tile type costs the same to enter
+    pub const fn uniform(cost: u32) -> Self {
+        MovementCosts {
+            land: Some(cost),
+            land_tree: Some(cost),
+            water: Some(cost),
+            terrain: Some(cost),
+            building: Some(cost),
+        }
+    }
+
+    pub fn with_cost(mut self, visual_type: &MapTileVisualType, cost: Option<u32>) -> Self {
+        assert!(
+            cost != Some(0),
+            "Movement cost needs to be greater than 0, or None to make the tile type impassable"
+        );
+        match visual_type {
+            MapTileVisualType::Land => self.land = cost,
+            MapTileVisualType::LandTree => self.land_tree = cost,
+            MapTileVisualType::Water => self.water = cost,
+            MapTileVisualType::Terrain => self.terrain = cost,
+            MapTileVisualType::Building => self.building = cost,
+        }
+        self
+    }
+
+    pub const fn get_cost(&self, visual_type: &MapTileVisualType) -> Option<u32> {
+        match visual_type {
+            MapTileVisualType::Land => self.land,
+            MapTileVisualType::LandTree => self.land_tree,
+            MapTileVisualType::Water => self.water,
+            MapTileVisualType::Terrain => self.terrain,
+            MapTileVisualType::Building => self.building,
+        }
+    }
+
+    /// Cheapest cost of any passable tile type, used to keep the A* heuristic admissible
+    fn get_min_cost(&self) -> u32 {
+        [
+            self.land,
+            self.land_tree,
+            self.water,
+            self.terrain,
+            self.building,
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(1)
+    }
+}
+impl Default for MovementCosts {
+    fn default() -> Self {
+        MovementCosts::uniform(1)
+    }
+}
+
+/// A path between two tiles, including both the start and goal tiles
+#[derive(Debug)]
+pub struct MapPath {
+    pub tiles: Vec<MapTileXY>,
+    /// Sum of the movement costs of every tile entered after the start tile
+    pub cost: u32,
+}
+
+/// Finds the cheapest path between two tiles using A*, or `None` if the goal can't be reached.
+///
+/// Movement is 4-directional, and a step is only allowed where the tile being left has its side of
+/// the shared edge unblocked (see: `MapTile::walkability_blocked`), so walls can run between two
+/// otherwise walkable tiles, and one-way edges are only followed in their direction. Ties are
+/// always broken the same way, so the server and client find identical paths for the same map.
+pub fn find_path(
+    map_grid: &MapGrid,
+    start: &MapTileXY,
+    goal: &MapTileXY,
+    movement_costs: &MovementCosts,
+) -> Option<MapPath> {
+    let width = map_grid.get_tiles_bounds().x as usize;
+    let height = map_grid.get_tiles_bounds().y as usize;
+    if start.x as usize >= width
+        || start.y as usize >= height
+        || goal.x as usize >= width
+        || goal.y as usize >= height
+    {
+        return None;
+    }
+
+    let start_index = (start.y as usize) * width + (start.x as usize);
+    let goal_index = (goal.y as usize) * width + (goal.x as usize);
+    let min_cost = movement_costs.get_min_cost();
+    let get_heuristic = |index: usize| -> u32 {
+        let dx = (index % width).abs_diff(goal.x as usize);
+        let dy = (index / width).abs_diff(goal.y as usize);
+        ((dx + dy) as u32).saturating_mul(min_cost)
+    };
+
+    const NO_PREVIOUS: usize = usize::MAX;
+    let mut costs = vec![u32::MAX; width * height];
+    let mut previous = vec![NO_PREVIOUS; width * height];
+    // Ordered by estimated total cost, then by the heuristic to prefer tiles nearer the goal, then
+    // by index to make ties deterministic
+    let mut open_set: BinaryHeap<Reverse<(u32, u32, usize)>> = BinaryHeap::new();
+
+    costs[start_index] = 0;
+    open_set.push(Reverse((
+        get_heuristic(start_index),
+        get_heuristic(start_index),
+        start_index,
+    )));
+    while let Some(Reverse((estimate, heuristic, index))) = open_set.pop() {
+        if index == goal_index {
+            break;
+        }
+        // Skip stale entries superseded by a cheaper path
+        if estimate - heuristic > costs[index] {
+            continue;
+        }
+
+        let tile = get_tile_at_index(map_grid, width, index);
+        for (neighbor_index, edge) in get_neighbor_edges(width, height, index) {
+            if !is_edge_walkable(tile, edge) {
+                continue;
+            }
+            let neighbor_tile = get_tile_at_index(map_grid, width, neighbor_index);
+            let step_cost = match movement_costs.get_cost(&neighbor_tile.visual_type) {
+                Some(step_cost) => step_cost,
+                None => continue,
+            };
+            let neighbor_cost = costs[index].saturating_add(step_cost);
+            if neighbor_cost < costs[neighbor_index] {
+                costs[neighbor_index] = neighbor_cost;
+                previous[neighbor_index] = index;
+                let neighbor_heuristic = get_heuristic(neighbor_index);
+                open_set.push(Reverse((
+                    neighbor_cost.saturating_add(neighbor_heuristic),
+                    neighbor_heuristic,
+                    neighbor_index,
+                )));
+            }
+        }
+    }
+
+    if costs[goal_index] == u32::MAX {
+        return None;
+    }
+
+    let mut tiles = vec![get_tile_xy_at_index(width, goal_index)];
+    let mut index = goal_index;
+    while previous[index] != NO_PREVIOUS {
+        index = previous[index];
+        tiles.push(get_tile_xy_at_index(width, index));
+    }
+    tiles.reverse();
+
+    Some(MapPath {
+        tiles,
+        cost: costs[goal_index],
+    })
+}
+
+/// Returns whether a unit can step directly from one tile to a neighboring tile
+pub fn can_step(map_grid: &MapGrid, from: &MapTileXY, to: &MapTileXY) -> bool {
+    let width = map_grid.get_tiles_bounds().x as usize;
+    let height = map_grid.get_tiles_bounds().y as usize;
+    if from.x as usize >= width || from.y as usize >= height {
+        return false;
+    }
+    let from_index = (from.y as usize) * width + (from.x as usize);
+    get_neighbor_edges(width, height, from_index)
+        .find(|(neighbor_index, _)| {
+            let neighbor_xy = get_tile_xy_at_index(width, *neighbor_index);
+            neighbor_xy.x == to.x && neighbor_xy.y == to.y
+        })
+        .map(|(_, edge)| is_edge_walkable(get_tile_at_index(map_grid, width, from_index), edge))
+        .unwrap_or(false)
+}
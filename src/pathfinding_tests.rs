@@ -0,0 +1,112 @@
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use effigy_shared::game::map::{MapChunkXY, MapGrid, MapTileVisualType, MapTileXY};
+
+use super::grid_index::get_tile_xy_at_index;
+use super::map_gen::MapGenerator;
+use super::pathfinding::{can_step, find_path, MovementCosts};
+use super::regions::{label_map_regions, RegionConnectivity};
+
+/// Builds a single chunk of open land with a wall along the right edges of column 3, which is
+/// open only at row 6
+fn create_walled_map_grid() -> MapGrid {
+    let mut grid = MapGrid::new(&MapChunkXY { x: 1, y: 1 });
+    for y in 0..grid.get_tiles_bounds().y {
+        if y == 6 {
+            continue;
+        }
+        grid.get_tile_mut(&MapTileXY { x: 3, y })
+            .unwrap()
+            .walkability_blocked
+            .right = true;
+    }
+    grid
+}
+
+#[test]
+fn find_path_respects_blocked_edges() {
+    let grid = create_walled_map_grid();
+    let start = MapTileXY { x: 3, y: 0 };
+    let goal = MapTileXY { x: 4, y: 0 };
+
+    assert!(!can_step(&grid, &start, &goal));
+
+    // The wall only blocks the shared edge, so the path walks up column 3 and back down column 4
+    let path = find_path(&grid, &start, &goal, &MovementCosts::default()).unwrap();
+    assert_eq!(path.cost, 13);
+    assert_eq!(path.tiles.len(), 14);
+    assert_eq!(path.tiles.first(), Some(&start));
+    assert_eq!(path.tiles.last(), Some(&goal));
+    assert!(path.tiles.contains(&MapTileXY { x: 3, y: 6 }));
+    assert!(path.tiles.contains(&MapTileXY { x: 4, y: 6 }));
+    for step in path.tiles.windows(2) {
+        assert!(can_step(&grid, &step[0], &step[1]));
+    }
+}
+
+#[test]
+fn find_path_uses_movement_costs() {
+    let mut grid = MapGrid::new(&MapChunkXY { x: 1, y: 1 });
+    for y in 0..grid.get_tiles_bounds().y {
+        grid.get_tile_mut(&MapTileXY { x: 2, y })
+            .unwrap()
+            .set_visual_type(&MapTileVisualType::LandTree);
+    }
+    let start = MapTileXY { x: 0, y: 0 };
+    let goal = MapTileXY { x: 4, y: 0 };
+
+    let path = find_path(&grid, &start, &goal, &MovementCosts::uniform(2)).unwrap();
+    assert_eq!(path.cost, 8);
+
+    let costs = MovementCosts::uniform(2).with_cost(&MapTileVisualType::LandTree, Some(5));
+    let path = find_path(&grid, &start, &goal, &costs).unwrap();
+    assert_eq!(path.cost, 11);
+
+    let costs = MovementCosts::uniform(2).with_cost(&MapTileVisualType::LandTree, None);
+    assert!(find_path(&grid, &start, &goal, &costs).is_none());
+}
+
+#[test]
+fn find_path_is_deterministic() {
+    // Of the three shortest paths on open land, ties are broken towards tiles nearer the goal, then
+    // the lowest index, so the path runs along the first row before stepping up
+    let grid = MapGrid::new(&MapChunkXY { x: 1, y: 1 });
+    let path = find_path(
+        &grid,
+        &MapTileXY { x: 0, y: 0 },
+        &MapTileXY { x: 2, y: 1 },
+        &MovementCosts::default(),
+    )
+    .unwrap();
+    assert_eq!(path.cost, 3);
+    assert_eq!(
+        path.tiles,
+        vec![
+            MapTileXY { x: 0, y: 0 },
+            MapTileXY { x: 1, y: 0 },
+            MapTileXY { x: 2, y: 0 },
+            MapTileXY { x: 2, y: 1 },
+        ]
+    );
+
+    let grid = MapGenerator::new(453537, &MapChunkXY { x: 2, y: 2 })
+        .with_terrain(0.2, 0.5)
+        .with_trees(0.2, 0.5)
+        .generate();
+    // The first and last tiles of the largest walkable region are always reachable
+    let regions = label_map_regions(&grid, RegionConnectivity::Walkability);
+    let largest_region = regions
+        .get_regions()
+        .iter()
+        .max_by_key(|region| region.area)
+        .unwrap();
+    let region_tile_indices = regions.get_region_tile_indices(largest_region.id);
+    let width = grid.get_tiles_bounds().x as usize;
+    let start = get_tile_xy_at_index(width, region_tile_indices[0]);
+    let goal = get_tile_xy_at_index(width, *region_tile_indices.last().unwrap());
+    let path = find_path(&grid, &start, &goal, &MovementCosts::default());
+    assert!(path.is_some());
+    let repeated_path = find_path(&grid, &start, &goal, &MovementCosts::default());
+    assert_eq!(path.unwrap().tiles, repeated_path.unwrap().tiles);
+}
@@ -0,0 +1,181 @@
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use effigy_shared::game::map::{
+    MapChunkXY, MapGrid, MapTileIndex, MapTileVisualType, MapTileXY, MAP_CHUNK_TILES_LENGTH,
+};
+
+use super::random::{hash_coords, SeededRng};
+
+/// Number of candidate points rolled per cell of the region grid
+const CANDIDATES_PER_CELL: u32 = 3;
+
+/// Kind of a point of interest, which also seeds its layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointOfInterestKind {
+    /// Stamped into the map as a single building tile when generating it, for evenly spaced
+    /// buildings unlike those of `MapGenerator::with_buildings`
+    Building = 0,
+    Camp = 1,
+    Chest = 2,
+}
+
+/// Configuration of one layer of points of interest.
+/// See: `MapGenerator::with_points_of_interest`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PointOfInterestLayer {
+    pub kind: PointOfInterestKind,
+    /// Minimum straight-line distance, in tiles, between two points of this layer
+    pub min_distance: f64,
+    /// Chance of each candidate point being considered, between 0.0 and 1.0 (inclusive)
+    pub density: f64,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct PointOfInterest {
+    pub kind: PointOfInterestKind,
+    pub tile: MapTileXY,
+}
+
+/// Seed `MapGenerator` places the points of interest of a map with, from the map's seed.
+/// See: `place_chunk_points_of_interest`
+pub const fn get_points_of_interest_seed(map_seed: u32) -> u32 {
+    map_seed.wrapping_add(8812345)
+}
+
+struct Candidate {
+    x: i64,
+    y: i64,
+    priority: u64,
+}
+
+/// Places the points of interest of a layer within a single chunk, on walkable land tiles.
+///
+/// Points are sampled with Poisson-disk spacing using a region grid: the world is divided into
+/// square cells of `min_distance` tiles, each rolling a few candidate points and priorities from
+/// only the seed and its cell coordinate. A candidate is kept if it is eligible and has the
+/// highest priority of all eligible candidates within `min_distance`. Since that only depends on
+/// the neighboring cells, a chunk always places the same points no matter which chunks were
+/// placed before it, and no two points are ever closer than `min_distance`, even across chunk
+/// borders.
+pub fn place_chunk_points_of_interest(
+    map_grid: &MapGrid,
+    seed: u32,
+    layer: &PointOfInterestLayer,
+    chunk_xy: &MapChunkXY,
+) -> Vec<PointOfInterest> {
+    assert!(
+        layer.density >= 0.0 && layer.density <= 1.0,
+        "Density value needs to be between 0.0 and 1.0 (inclusive)"
+    );
+    assert!(
+        layer.min_distance >= 1.0,
+        "Minimum distance needs to be at least 1 tile"
+    );
+
+    let layer_seed = hash_coords(seed, layer.kind as i64, 0) as u32;
+    let cell_length = layer.min_distance.ceil() as i64;
+    let min_distance_squared = layer.min_distance * layer.min_distance;
+
+    let chunk_length = MAP_CHUNK_TILES_LENGTH as i64;
+    let chunk_min = (
+        chunk_xy.x as i64 * chunk_length,
+        chunk_xy.y as i64 * chunk_length,
+    );
+    let chunk_max = (chunk_min.0 + chunk_length, chunk_min.1 + chunk_length);
+
+    let is_eligible = |x: i64, y: i64| -> bool {
+        if x < 0 || y < 0 {
+            return false;
+        }
+        match map_grid.get_tile(&MapTileXY {
+            x: x as MapTileIndex,
+            y: y as MapTileIndex,
+        }) {
+            Some(tile) => tile.visual_type == MapTileVisualType::Land,
+            None => false,
+        }
+    };
+    let get_cell_candidates = |cell_x: i64, cell_y: i64| -> Vec<Candidate> {
+        let mut rng = SeededRng::new(hash_coords(layer_seed, cell_x, cell_y));
+        let mut candidates = Vec::with_capacity(CANDIDATES_PER_CELL as usize);
+        for _ in 0..CANDIDATES_PER_CELL {
+            // Always roll every value so a candidate never shifts the ones after it
+            let x = cell_x * cell_length + rng.next_below(cell_length as u32) as i64;
+            let y = cell_y * cell_length + rng.next_below(cell_length as u32) as i64;
+            let priority = rng.next_u64();
+            let is_active = rng.next_f64() < layer.density;
+            if is_active && is_eligible(x, y) {
+                candidates.push(Candidate { x, y, priority });
+            }
+        }
+        candidates
+    };
+
+    // Candidates of every cell overlapping the chunk and the cells around them, rolled once
+    let cells_min = (
+        chunk_min.0.div_euclid(cell_length),
+        chunk_min.1.div_euclid(cell_length),
+    );
+    let cells_max = (
+        (chunk_max.0 - 1).div_euclid(cell_length),
+        (chunk_max.1 - 1).div_euclid(cell_length),
+    );
+    let buckets_width = (cells_max.0 - cells_min.0 + 3) as usize;
+    let buckets: Vec<Vec<Candidate>> = ((cells_min.1 - 1)..=(cells_max.1 + 1))
+        .flat_map(|cell_y| ((cells_min.0 - 1)..=(cells_max.0 + 1)).map(move |x| (x, cell_y)))
+        .map(|(cell_x, cell_y)| get_cell_candidates(cell_x, cell_y))
+        .collect();
+    let get_bucket = |cell_x: i64, cell_y: i64| -> &[Candidate] {
+        let bucket_x = (cell_x - cells_min.0 + 1) as usize;
+        let bucket_y = (cell_y - cells_min.1 + 1) as usize;
+        &buckets[bucket_y * buckets_width + bucket_x]
+    };
+
+    let mut points_of_interest: Vec<PointOfInterest> = Vec::new();
+    for cell_y in cells_min.1..=cells_max.1 {
+        for cell_x in cells_min.0..=cells_max.0 {
+            for candidate in get_bucket(cell_x, cell_y) {
+                if candidate.x < chunk_min.0
+                    || candidate.y < chunk_min.1
+                    || candidate.x >= chunk_max.0
+                    || candidate.y >= chunk_max.1
+                {
+                    continue;
+                }
+
+                // Cells are as wide as the minimum distance, so only neighboring cells can conflict.
+                // This includes comparing the candidate against itself, which never suppresses it.
+                let mut is_highest_priority = true;
+                'neighbors: for neighbor_cell_y in (cell_y - 1)..=(cell_y + 1) {
+                    for neighbor_cell_x in (cell_x - 1)..=(cell_x + 1) {
+                        for other in get_bucket(neighbor_cell_x, neighbor_cell_y) {
+                            let dx = (other.x - candidate.x) as f64;
+                            let dy = (other.y - candidate.y) as f64;
+                            if dx * dx + dy * dy < min_distance_squared
+                                && (other.priority, other.y, other.x)
+                                    > (candidate.priority, candidate.y, candidate.x)
+                            {
+                                is_highest_priority = false;
+                                break 'neighbors;
+                            }
+                        }
+                    }
+                }
+                if is_highest_priority {
+                    points_of_interest.push(PointOfInterest {
+                        kind: layer.kind,
+                        tile: MapTileXY {
+                            x: candidate.x as MapTileIndex,
+                            y: candidate.y as MapTileIndex,
+                        },
+                    });
+                }
+            }
+        }
+    }
+
+    // Scan order within the chunk
+    points_of_interest.sort_by_key(|poi| (poi.tile.y, poi.tile.x));
+    points_of_interest
+}
@@ -0,0 +1,94 @@
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use effigy_shared::game::map::{MapChunkXY, MapTileVisualType};
+
+use super::map_gen::MapGenerator;
+use super::points_of_interest::{
+    get_points_of_interest_seed, place_chunk_points_of_interest, PointOfInterestKind,
+    PointOfInterestLayer,
+};
+
+#[test]
+fn place_points_of_interest_independent_of_chunk_order() {
+    let seed: u32 = 453537;
+    let min_distance: f64 = 9.5;
+    let generated = MapGenerator::new(seed, &MapChunkXY { x: 3, y: 3 })
+        .with_terrain(0.2, 0.5)
+        .with_water(0.2, 0.5)
+        .with_points_of_interest(PointOfInterestKind::Camp, min_distance, 0.8)
+        .generate_full();
+    let points_of_interest = &generated.points_of_interest;
+    assert!(!points_of_interest.is_empty());
+
+    for (i, poi) in points_of_interest.iter().enumerate() {
+        assert_eq!(
+            generated.map_grid.get_tile(&poi.tile).unwrap().visual_type,
+            MapTileVisualType::Land
+        );
+        // Spacing holds across chunk borders
+        for other in &points_of_interest[(i + 1)..] {
+            let dx = poi.tile.x.abs_diff(other.tile.x) as f64;
+            let dy = poi.tile.y.abs_diff(other.tile.y) as f64;
+            assert!(dx * dx + dy * dy >= min_distance * min_distance);
+        }
+    }
+
+    // Placing chunks one at a time, in reverse order, finds the same points
+    let layer = PointOfInterestLayer {
+        kind: PointOfInterestKind::Camp,
+        min_distance,
+        density: 0.8,
+    };
+    let mut chunk_points_of_interest = Vec::new();
+    for chunk_y in (0..3).rev() {
+        for chunk_x in (0..3).rev() {
+            let chunk_xy = MapChunkXY {
+                x: chunk_x,
+                y: chunk_y,
+            };
+            chunk_points_of_interest.extend(place_chunk_points_of_interest(
+                &generated.map_grid,
+                get_points_of_interest_seed(seed),
+                &layer,
+                &chunk_xy,
+            ));
+        }
+    }
+    assert_eq!(chunk_points_of_interest.len(), points_of_interest.len());
+    for poi in &chunk_points_of_interest {
+        assert!(points_of_interest.contains(poi));
+    }
+}
+
+#[test]
+fn stamp_building_points_of_interest() {
+    let generated = MapGenerator::new(453537, &MapChunkXY { x: 2, y: 2 })
+        .with_terrain(0.2, 0.5)
+        .with_points_of_interest(PointOfInterestKind::Building, 6.0, 0.8)
+        .with_points_of_interest(PointOfInterestKind::Chest, 6.0, 0.8)
+        .generate_full();
+    let building_points: Vec<_> = generated
+        .points_of_interest
+        .iter()
+        .filter(|poi| poi.kind == PointOfInterestKind::Building)
+        .collect();
+    assert!(!building_points.is_empty());
+    assert!(generated
+        .points_of_interest
+        .iter()
+        .any(|poi| poi.kind == PointOfInterestKind::Chest));
+
+    // Every building point is a building of its own, and the only buildings on the map
+    let buildings = generated.buildings.get_buildings();
+    assert_eq!(buildings.len(), building_points.len());
+    for poi in building_points {
+        assert_eq!(
+            generated.map_grid.get_tile(&poi.tile).unwrap().visual_type,
+            MapTileVisualType::Building
+        );
+        let building = generated.buildings.get_building_at(&poi.tile).unwrap();
+        assert_eq!(building.footprint.len(), 1);
+        assert_eq!(building.footprint[0], poi.tile);
+    }
+}
@@ -0,0 +1,260 @@
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use effigy_shared::game::map::{MapGrid, MapTileIndex, MapTileVisualType, MapTileXY, SquareBounds};
+
+use super::grid_index::{get_visual_type_from_char, NO_BLOCKED_EDGES};
+use super::random::hash_coords;
+
+/// Authored pattern of tiles, such as a town square or a boss arena, stamped into the map.
+/// See: `MapGenerator::with_prefab`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prefab {
+    width: usize,
+    height: usize,
+    /// Tile type of each cell, row-major from the lowest x and y, or `None` for "don't care" cells
+    /// which keep whatever the map has
+    cells: Vec<Option<MapTileVisualType>>,
+}
+impl Prefab {
+    pub fn new(width: usize, height: usize, cells: Vec<Option<MapTileVisualType>>) -> Self {
+        assert!(
+            width > 0 && height > 0,
+            "Prefab needs to be at least 1 tile wide and high"
+        );
+        assert_eq!(
+            cells.len(),
+            width * height,
+            "Expected a cell per tile of the prefab"
+        );
+        Prefab {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    /// Parses a prefab from rows of equal length, the first row being the lowest y, with a
+    /// character per cell as in a text map (see: `effigy_gen::text_map`), or `?` for "don't care"
+    /// cells
+    pub fn from_rows(rows: &[&str]) -> Self {
+        let width = rows.first().map_or(0, |row| row.chars().count());
+        let cells = rows
+            .iter()
+            .flat_map(|row| {
+                assert_eq!(
+                    row.chars().count(),
+                    width,
+                    "Expected every row of the prefab to be the same length"
+                );
+                row.chars().map(|cell| match cell {
+                    '?' => None,
+                    _ => Some(
+                        get_visual_type_from_char(cell)
+                            .unwrap_or_else(|| panic!("Unknown prefab cell '{}'", cell)),
+                    ),
+                })
+            })
+            .collect();
+        Prefab::new(width, rows.len(), cells)
+    }
+
+    pub const fn get_width(&self) -> usize {
+        self.width
+    }
+
+    pub const fn get_height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get_cell(&self, x: usize, y: usize) -> Option<&MapTileVisualType> {
+        self.cells[y * self.width + x].as_ref()
+    }
+}
+
+/// Where a prefab is stamped into the map
+#[derive(Debug, Clone, PartialEq)]
+pub enum PrefabPlacement {
+    /// Once with its lowest x and y cell at the tile, overriding anything there
+    At { x: u32, y: u32 },
+    /// Up to `count` times at sites picked from the seed, where none of its cells would cover
+    /// water, terrain or another prefab, at least `min_distance` tiles apart
+    Scattered { count: usize, min_distance: f64 },
+}
+
+/// Configuration of one prefab added to the map.
+/// See: `MapGenerator::with_prefab`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrefabLayer {
+    pub prefab: Prefab,
+    pub placement: PrefabPlacement,
+}
+
+/// A prefab stamped into the map
+#[derive(Debug, PartialEq)]
+pub struct PrefabSite {
+    /// Index of the prefab, in the order they were added to the `MapGenerator`
+    pub layer_index: usize,
+    /// Tile of the prefab's lowest x and y cell
+    pub tile: MapTileXY,
+}
+
+/// Stamps the prefab of every layer into the map in order, returning where each was placed.
+///
+/// Every cell other than "don't care" replaces its tile, clearing any layer there, and building
+/// cells are keyed as a single building noise cell per site in `cell_keys`. Walkability of the
+/// stamped tiles is reset like a freshly placed layer, so it needs to be simplified afterwards.
+pub(crate) fn place_prefabs(
+    map_grid: &mut MapGrid,
+    cell_keys: &mut [Option<u64>],
+    seed: u32,
+    layers: &[PrefabLayer],
+) -> Vec<PrefabSite> {
+    let width = map_grid.get_tiles_bounds().x as usize;
+    let height = map_grid.get_tiles_bounds().y as usize;
+
+    // Tiles covered by a cell of any prefab placed so far, which scattered prefabs avoid
+    let mut is_occupied = vec![false; width * height];
+    let mut sites: Vec<PrefabSite> = Vec::new();
+    for (layer_index, layer) in layers.iter().enumerate() {
+        let start_time = {
+            info!("Placing prefab: {:?} ...", layer.placement);
+            std::time::Instant::now()
+        };
+        let prefab = &layer.prefab;
+        let origins: Vec<(usize, usize)> = match &layer.placement {
+            PrefabPlacement::At { x, y } => vec![(*x as usize, *y as usize)],
+            PrefabPlacement::Scattered {
+                count,
+                min_distance,
+            } => find_scattered_sites(
+                map_grid,
+                &is_occupied,
+                hash_coords(seed, layer_index as i64, 0) as u32,
+                prefab,
+                *count,
+                *min_distance,
+            ),
+        };
+
+        for (origin_x, origin_y) in origins {
+            let cell_key = hash_coords(seed, sites.len() as i64, -1);
+            for prefab_y in 0..prefab.height {
+                for prefab_x in 0..prefab.width {
+                    let visual_type = match prefab.get_cell(prefab_x, prefab_y) {
+                        Some(visual_type) => visual_type,
+                        None => continue,
+                    };
+                    let (x, y) = (origin_x + prefab_x, origin_y + prefab_y);
+                    let walkability_blocked = if *visual_type == MapTileVisualType::Land {
+                        NO_BLOCKED_EDGES
+                    } else {
+                        SquareBounds::ALL
+                    };
+                    map_grid
+                        .get_tile_mut(&MapTileXY {
+                            x: x as MapTileIndex,
+                            y: y as MapTileIndex,
+                        })
+                        .unwrap()
+                        .set_visual_type(visual_type)
+                        .set_walkability_blocked(&walkability_blocked);
+                    cell_keys[y * width + x] = if *visual_type == MapTileVisualType::Building {
+                        Some(cell_key)
+                    } else {
+                        None
+                    };
+                    is_occupied[y * width + x] = true;
+                }
+            }
+            sites.push(PrefabSite {
+                layer_index,
+                tile: MapTileXY {
+                    x: origin_x as MapTileIndex,
+                    y: origin_y as MapTileIndex,
+                },
+            });
+        }
+
+        info!(
+            "... done placing prefab at {} sites! Completed in {:.3} seconds",
+            sites
+                .iter()
+                .filter(|site| site.layer_index == layer_index)
+                .count(),
+            start_time.elapsed().as_secs_f32()
+        );
+    }
+    sites
+}
+
+/// Picks sites in order of a priority hashed from the seed and each site's origin, skipping sites
+/// which cover water, terrain or an occupied tile, or are too close to a site already picked
+fn find_scattered_sites(
+    map_grid: &MapGrid,
+    is_occupied: &[bool],
+    seed: u32,
+    prefab: &Prefab,
+    count: usize,
+    min_distance: f64,
+) -> Vec<(usize, usize)> {
+    let width = map_grid.get_tiles_bounds().x as usize;
+    let height = map_grid.get_tiles_bounds().y as usize;
+
+    let is_valid_site = |origin_x: usize, origin_y: usize| -> bool {
+        (0..prefab.height).all(|prefab_y| {
+            (0..prefab.width).all(|prefab_x| {
+                if prefab.get_cell(prefab_x, prefab_y).is_none() {
+                    return true;
+                }
+                let (x, y) = (origin_x + prefab_x, origin_y + prefab_y);
+                let tile = map_grid
+                    .get_tile(&MapTileXY {
+                        x: x as MapTileIndex,
+                        y: y as MapTileIndex,
+                    })
+                    .unwrap();
+                !is_occupied[y * width + x]
+                    && tile.visual_type != MapTileVisualType::Water
+                    && tile.visual_type != MapTileVisualType::Terrain
+            })
+        })
+    };
+
+    let mut candidates: Vec<(u64, usize, usize)> = (0..=(height - prefab.height))
+        .flat_map(|y| (0..=(width - prefab.width)).map(move |x| (x, y)))
+        .map(|(x, y)| (hash_coords(seed, x as i64, y as i64), x, y))
+        .collect();
+    candidates.sort_unstable();
+
+    let min_distance_squared = min_distance * min_distance;
+    let mut sites: Vec<(usize, usize)> = Vec::with_capacity(count);
+    for (_, x, y) in candidates {
+        if sites.len() >= count {
+            break;
+        }
+        let is_far_enough = sites.iter().all(|&(site_x, site_y)| {
+            let distance_x = x as f64 - site_x as f64;
+            let distance_y = y as f64 - site_y as f64;
+            distance_x * distance_x + distance_y * distance_y >= min_distance_squared
+        });
+        // Tiles are only marked occupied once the whole layer is picked, so check its own sites
+        let overlaps_site = sites.iter().any(|&(site_x, site_y)| {
+            x < site_x + prefab.width
+                && site_x < x + prefab.width
+                && y < site_y + prefab.height
+                && site_y < y + prefab.height
+        });
+        if is_far_enough && !overlaps_site && is_valid_site(x, y) {
+            sites.push((x, y));
+        }
+    }
+    if sites.len() < count {
+        debug!(
+            "Only found {} of {} valid sites for prefab",
+            sites.len(),
+            count
+        );
+    }
+    sites
+}
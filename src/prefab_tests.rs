@@ -0,0 +1,104 @@
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use effigy_shared::game::map::{MapChunkXY, MapTileVisualType, MapTileXY};
+
+use super::map_gen::MapGenerator;
+use super::prefab::{Prefab, PrefabPlacement};
+
+fn get_example_generator() -> MapGenerator {
+    MapGenerator::new(453537, &MapChunkXY { x: 2, y: 2 })
+        .with_terrain(0.2, 0.5)
+        .with_water(0.2, 0.5)
+        .with_trees(0.2, 0.5)
+}
+
+#[test]
+fn stamp_prefab_at_tile() {
+    let arena = Prefab::from_rows(&[
+        "#####", //
+        "#...#", //
+        "#.?.#", //
+        "#...#", //
+        "#####", //
+    ]);
+    let baseline = get_example_generator().generate();
+    let generated = get_example_generator()
+        .with_prefab(arena, PrefabPlacement::At { x: 10, y: 20 })
+        .generate_full();
+    assert_eq!(generated.prefab_sites.len(), 1);
+    assert_eq!(generated.prefab_sites[0].tile, MapTileXY { x: 10, y: 20 });
+
+    let map_grid = &generated.map_grid;
+    let get_tile = |x, y| map_grid.get_tile(&MapTileXY { x, y }).unwrap();
+    assert_eq!(get_tile(10, 20).visual_type, MapTileVisualType::Terrain);
+    assert_eq!(get_tile(14, 24).visual_type, MapTileVisualType::Terrain);
+    assert_eq!(get_tile(11, 21).visual_type, MapTileVisualType::Land);
+    // The "don't care" cell keeps the generated tile
+    assert_eq!(
+        get_tile(12, 22).visual_type,
+        baseline
+            .get_tile(&MapTileXY { x: 12, y: 22 })
+            .unwrap()
+            .visual_type
+    );
+
+    // Walkability is recomputed around the stamp
+    let corner = &get_tile(11, 21).walkability_blocked;
+    assert!(corner.left && corner.down);
+    assert!(!corner.right && !corner.up);
+}
+
+#[test]
+fn scatter_prefab_on_open_ground() {
+    let min_distance: f64 = 12.0;
+    let plaza = Prefab::from_rows(&[
+        "BB?BB", //
+        "B...B", //
+        "?.T.?", //
+        "B...B", //
+        "BB?BB", //
+    ]);
+    let placement = PrefabPlacement::Scattered {
+        count: 6,
+        min_distance,
+    };
+    let baseline = get_example_generator().generate();
+    let generated = get_example_generator()
+        .with_prefab(plaza.clone(), placement.clone())
+        .generate_full();
+    let sites = &generated.prefab_sites;
+    assert!(!sites.is_empty() && sites.len() <= 6);
+
+    for (i, site) in sites.iter().enumerate() {
+        for y in 0..plaza.get_height() as u32 {
+            for x in 0..plaza.get_width() as u32 {
+                let tile_xy = MapTileXY {
+                    x: site.tile.x + x,
+                    y: site.tile.y + y,
+                };
+                let baseline_type = &baseline.get_tile(&tile_xy).unwrap().visual_type;
+                let visual_type = &generated.map_grid.get_tile(&tile_xy).unwrap().visual_type;
+                match plaza.get_cell(x as usize, y as usize) {
+                    Some(cell) => {
+                        assert_ne!(baseline_type, &MapTileVisualType::Water);
+                        assert_ne!(baseline_type, &MapTileVisualType::Terrain);
+                        assert_eq!(visual_type, cell);
+                    }
+                    None => assert_eq!(visual_type, baseline_type),
+                }
+            }
+        }
+        for other in &sites[(i + 1)..] {
+            let dx = site.tile.x.abs_diff(other.tile.x) as f64;
+            let dy = site.tile.y.abs_diff(other.tile.y) as f64;
+            assert!(dx * dx + dy * dy >= min_distance * min_distance);
+        }
+    }
+
+    // Sites only depend on the seed and the map
+    let regenerated = get_example_generator()
+        .with_prefab(plaza, placement)
+        .generate_full();
+    assert_eq!(regenerated.prefab_sites, generated.prefab_sites);
+}
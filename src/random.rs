@@ -0,0 +1,57 @@
+//! Small deterministic hashing and random number helpers.
+//!
+//! Generation must be identical across platforms (native server and WebAssembly client), so
+//! these avoid any platform-dependent or globally seeded sources of randomness.
+
+/// Hashes a seed and a 2D integer coordinate into a well-distributed 64-bit value
+pub(crate) const fn hash_coords(seed: u32, x: i64, y: i64) -> u64 {
+    let mut hash = (seed as u64) ^ 0x9E37_79B9_7F4A_7C15;
+    hash = splitmix64(hash ^ (x as u64));
+    hash = splitmix64(hash ^ (y as u64).rotate_left(32));
+    hash
+}
+
+const fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// A tiny deterministic pseudo-random number generator (SplitMix64)
+pub(crate) struct SeededRng {
+    state: u64,
+}
+impl SeededRng {
+    pub(crate) const fn new(seed: u64) -> Self {
+        SeededRng { state: seed }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        splitmix64(self.state)
+    }
+
+    /// Returns a value in `0.0..1.0`
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        // Use the top 53 bits to fill the mantissa exactly
+        ((self.next_u64() >> 11) as f64) / ((1u64 << 53) as f64)
+    }
+
+    /// Returns a value in `0..upper_bound`
+    pub(crate) fn next_below(&mut self, upper_bound: u32) -> u32 {
+        assert!(upper_bound > 0, "Upper bound needs to be greater than 0");
+        (((self.next_u64() >> 32) * (upper_bound as u64)) >> 32) as u32
+    }
+}
+
+/// Mixes bytes into a running 64-bit hash, e.g. to fingerprint a sequence of values
+pub(crate) const fn hash_bytes(hash: u64, bytes: &[u8]) -> u64 {
+    let mut hash = splitmix64(hash ^ (bytes.len() as u64));
+    let mut index = 0;
+    while index < bytes.len() {
+        hash = splitmix64(hash ^ (bytes[index] as u64));
+        index += 1;
+    }
+    hash
+}
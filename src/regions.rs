@@ -0,0 +1,190 @@
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use std::collections::VecDeque;
+
+use effigy_shared::game::map::{MapGrid, MapTileIndex, MapTileVisualType, MapTileXY};
+
+use super::grid_index::{get_neighbor_edges, get_tile_at_index, is_edge_walkable_both_ways};
+
+pub type MapRegionId = usize;
+
+/// Which neighboring tiles belong to the same region
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionConnectivity {
+    /// Neighboring tiles with the same `MapTileVisualType`, e.g. a lake or forest patch
+    VisualType,
+    /// Neighboring tiles whose shared edge isn't blocked on either side, e.g. a walkable area.
+    /// Tiles blocked on all sides form a region of their own, and one-way edges never join
+    /// regions.
+    Walkability,
+}
+
+/// A connected region of tiles and its metadata
+#[derive(Debug)]
+pub struct MapRegion {
+    pub id: MapRegionId,
+    /// Visual type of the first tile of the region in scan order
+    pub visual_type: MapTileVisualType,
+    /// Number of tiles in the region
+    pub area: usize,
+    /// Lower-left corner of the bounding box (inclusive)
+    pub bounds_min: MapTileXY,
+    /// Upper-right corner of the bounding box (inclusive)
+    pub bounds_max: MapTileXY,
+    /// Mean tile position of the region, which may not lie on a tile of the region
+    pub centroid: (f64, f64),
+    /// Whether any tile of the region is on the outer edge of the map
+    pub touches_map_edge: bool,
+}
+
+/// Region labeling of every tile of a `MapGrid`.
+/// See: `label_map_regions`
+pub struct MapRegions {
+    tiles_bounds: MapTileXY,
+    /// Region ID per tile, row-major
+    tile_region_ids: Vec<MapRegionId>,
+    /// Row-major tile indices of every region, grouped by region in ID order
+    region_tile_indices: Vec<usize>,
+    /// Start of each region's tiles in `region_tile_indices`, plus the end of the last region
+    region_tile_offsets: Vec<usize>,
+    regions: Vec<MapRegion>,
+}
+impl MapRegions {
+    /// Returns the ID of the region containing the tile, or `None` if the tile is out of bounds
+    pub fn get_region_id(&self, tile_xy: &MapTileXY) -> Option<MapRegionId> {
+        if tile_xy.x >= self.tiles_bounds.x || tile_xy.y >= self.tiles_bounds.y {
+            return None;
+        }
+        let index = (tile_xy.y as usize) * (self.tiles_bounds.x as usize) + (tile_xy.x as usize);
+        Some(self.tile_region_ids[index])
+    }
+
+    pub fn get_region(&self, region_id: MapRegionId) -> Option<&MapRegion> {
+        self.regions.get(region_id)
+    }
+
+    /// Returns the region containing the tile, or `None` if the tile is out of bounds
+    pub fn get_region_at(&self, tile_xy: &MapTileXY) -> Option<&MapRegion> {
+        self.get_region_id(tile_xy)
+            .and_then(|region_id| self.get_region(region_id))
+    }
+
+    /// All regions, ordered by ID. IDs are assigned in scan order (row by row from the origin).
+    pub fn get_regions(&self) -> &[MapRegion] {
+        &self.regions
+    }
+
+    /// Region ID per tile, row-major
+    pub fn get_tile_region_ids(&self) -> &[MapRegionId] {
+        &self.tile_region_ids
+    }
+
+    /// Returns the row-major tile indices of a region in ascending order, or an empty slice if
+    /// there's no such region
+    pub fn get_region_tile_indices(&self, region_id: MapRegionId) -> &[usize] {
+        if region_id >= self.regions.len() {
+            return &[];
+        }
+        &self.region_tile_indices
+            [self.region_tile_offsets[region_id]..self.region_tile_offsets[region_id + 1]]
+    }
+}
+
+/// Labels every tile of the map with the ID of the connected region it belongs to.
+///
+/// Regions are 4-connected and labeled deterministically in scan order, so the same map always
+/// produces the same IDs.
+pub fn label_map_regions(map_grid: &MapGrid, connectivity: RegionConnectivity) -> MapRegions {
+    let width = map_grid.get_tiles_bounds().x as usize;
+    let height = map_grid.get_tiles_bounds().y as usize;
+
+    const UNLABELED: MapRegionId = MapRegionId::MAX;
+    let mut tile_region_ids = vec![UNLABELED; width * height];
+    let mut regions: Vec<MapRegion> = Vec::new();
+    let mut region_tile_indices: Vec<usize> = Vec::with_capacity(width * height);
+    let mut region_tile_offsets: Vec<usize> = vec![0];
+    let mut queue: VecDeque<usize> = VecDeque::new();
+    for start_index in 0..(width * height) {
+        if tile_region_ids[start_index] != UNLABELED {
+            continue;
+        }
+        let region_id = regions.len();
+        let start_tile = get_tile_at_index(map_grid, width, start_index);
+
+        let mut area: usize = 0;
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (width, height, 0, 0);
+        let (mut sum_x, mut sum_y) = (0.0, 0.0);
+        let mut touches_map_edge = false;
+
+        tile_region_ids[start_index] = region_id;
+        queue.push_back(start_index);
+        while let Some(index) = queue.pop_front() {
+            let (x, y) = (index % width, index / width);
+            region_tile_indices.push(index);
+            area += 1;
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+            sum_x += x as f64;
+            sum_y += y as f64;
+            touches_map_edge |= x == 0 || y == 0 || x + 1 == width || y + 1 == height;
+
+            let tile = get_tile_at_index(map_grid, width, index);
+            for (neighbor_index, edge) in get_neighbor_edges(width, height, index) {
+                if tile_region_ids[neighbor_index] != UNLABELED {
+                    continue;
+                }
+                let neighbor_tile = get_tile_at_index(map_grid, width, neighbor_index);
+                let is_connected = match connectivity {
+                    RegionConnectivity::VisualType => {
+                        neighbor_tile.visual_type == start_tile.visual_type
+                    }
+                    RegionConnectivity::Walkability => {
+                        is_edge_walkable_both_ways(tile, neighbor_tile, edge)
+                    }
+                };
+                if is_connected {
+                    tile_region_ids[neighbor_index] = region_id;
+                    queue.push_back(neighbor_index);
+                }
+            }
+        }
+
+        let region_start = region_tile_offsets[region_id];
+        region_tile_indices[region_start..].sort_unstable();
+        region_tile_offsets.push(region_tile_indices.len());
+
+        regions.push(MapRegion {
+            id: region_id,
+            visual_type: start_tile.visual_type.clone(),
+            area,
+            bounds_min: MapTileXY {
+                x: min_x as MapTileIndex,
+                y: min_y as MapTileIndex,
+            },
+            bounds_max: MapTileXY {
+                x: max_x as MapTileIndex,
+                y: max_y as MapTileIndex,
+            },
+            centroid: (sum_x / area as f64, sum_y / area as f64),
+            touches_map_edge,
+        });
+    }
+
+    debug!(
+        "Labeled {} regions by {:?} over {} tiles",
+        regions.len(),
+        connectivity,
+        width * height
+    );
+
+    MapRegions {
+        tiles_bounds: map_grid.get_tiles_bounds(),
+        tile_region_ids,
+        region_tile_indices,
+        region_tile_offsets,
+        regions,
+    }
+}
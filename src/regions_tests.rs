@@ -0,0 +1,134 @@
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use effigy_shared::game::map::{
+    MapChunkXY, MapGrid, MapTileVisualType, MapTileXY, SquareBounds, MAP_CHUNK_TILES_LENGTH,
+};
+
+use super::map_gen::MapGenerator;
+use super::regions::{label_map_regions, RegionConnectivity};
+use super::text_map::parse_map_text;
+
+/// Builds a single chunk map of land with a 3x2 lake at (4, 5) and a lone water tile at the origin
+fn create_lake_map_grid() -> MapGrid {
+    let mut grid = MapGrid::new(&MapChunkXY { x: 1, y: 1 });
+    let water_tiles = [(4, 5), (5, 5), (6, 5), (4, 6), (5, 6), (6, 6), (0, 0)];
+    for (x, y) in water_tiles {
+        grid.get_tile_mut(&MapTileXY { x, y })
+            .unwrap()
+            .set_visual_type(&MapTileVisualType::Water)
+            .set_walkability_blocked(&SquareBounds::ALL);
+    }
+    grid
+}
+
+#[test]
+fn label_regions_by_visual_type() {
+    let grid = create_lake_map_grid();
+    let regions = label_map_regions(&grid, RegionConnectivity::VisualType);
+
+    // Scan order: the origin water tile, the surrounding land, then the lake
+    assert_eq!(regions.get_regions().len(), 3);
+
+    let origin = regions.get_region_at(&MapTileXY { x: 0, y: 0 }).unwrap();
+    assert_eq!(origin.id, 0);
+    assert_eq!(origin.area, 1);
+    assert!(origin.touches_map_edge);
+
+    let land = regions.get_region_at(&MapTileXY { x: 1, y: 0 }).unwrap();
+    assert_eq!(land.visual_type, MapTileVisualType::Land);
+    assert_eq!(
+        land.area,
+        (MAP_CHUNK_TILES_LENGTH as usize) * (MAP_CHUNK_TILES_LENGTH as usize) - 7
+    );
+
+    let lake = regions.get_region_at(&MapTileXY { x: 5, y: 6 }).unwrap();
+    assert_eq!(lake.id, 2);
+    assert_eq!(lake.visual_type, MapTileVisualType::Water);
+    assert_eq!(lake.area, 6);
+    assert_eq!(lake.bounds_min, MapTileXY { x: 4, y: 5 });
+    assert_eq!(lake.bounds_max, MapTileXY { x: 6, y: 6 });
+    assert_eq!(lake.centroid, (5.0, 5.5));
+    assert!(!lake.touches_map_edge);
+
+    // Tile indices are row-major and ascending
+    let width = MAP_CHUNK_TILES_LENGTH as usize;
+    assert_eq!(regions.get_region_tile_indices(origin.id), &[0]);
+    assert_eq!(
+        regions.get_region_tile_indices(lake.id),
+        &[
+            5 * width + 4,
+            5 * width + 5,
+            5 * width + 6,
+            6 * width + 4,
+            6 * width + 5,
+            6 * width + 6
+        ]
+    );
+    assert_eq!(regions.get_region_tile_indices(land.id).len(), land.area);
+    assert!(regions.get_region_tile_indices(3).is_empty());
+}
+
+#[test]
+fn label_regions_by_walkability() {
+    let grid = MapGenerator::new(453537, &MapChunkXY { x: 2, y: 2 })
+        .with_terrain(0.2, 0.5)
+        .with_water(0.2, 0.5)
+        .generate();
+    let regions = label_map_regions(&grid, RegionConnectivity::Walkability);
+
+    // Every tile is labeled, and the areas add up to the whole map
+    let total_area: usize = regions.get_regions().iter().map(|region| region.area).sum();
+    assert_eq!(total_area, grid.get_tiles_count());
+
+    // Tiles are always joined across an edge open in both directions
+    let width = grid.get_tiles_bounds().x;
+    for y in 0..grid.get_tiles_bounds().y {
+        for x in 0..(width - 1) {
+            let tile = grid.get_tile(&MapTileXY { x, y }).unwrap();
+            let right_tile = grid.get_tile(&MapTileXY { x: x + 1, y }).unwrap();
+            if tile.walkability_blocked.right || right_tile.walkability_blocked.left {
+                continue;
+            }
+            assert_eq!(
+                regions.get_region_id(&MapTileXY { x, y }),
+                regions.get_region_id(&MapTileXY { x: x + 1, y })
+            );
+        }
+    }
+}
+
+#[test]
+fn label_regions_across_one_way_edges() {
+    // Units can step from the left tile into the right one, but not back
+    let grid = parse_map_text(
+        "
+┌───┐
+│.>.│
+└───┘
+",
+        true,
+    )
+    .unwrap();
+    let regions = label_map_regions(&grid, RegionConnectivity::Walkability);
+    assert_ne!(
+        regions.get_region_id(&MapTileXY { x: 0, y: 0 }),
+        regions.get_region_id(&MapTileXY { x: 1, y: 0 })
+    );
+
+    // The same tiles join when the edge is open both ways
+    let grid = parse_map_text(
+        "
+┌───┐
+│. .│
+└───┘
+",
+        true,
+    )
+    .unwrap();
+    let regions = label_map_regions(&grid, RegionConnectivity::Walkability);
+    assert_eq!(
+        regions.get_region_id(&MapTileXY { x: 0, y: 0 }),
+        regions.get_region_id(&MapTileXY { x: 1, y: 0 })
+    );
+}
@@ -0,0 +1,170 @@
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use std::collections::HashSet;
+
+use noise::NoiseFn;
+
+use effigy_shared::game::map::{MapChunkXY, MAP_CHUNK_TILES_LENGTH};
+
+use super::noise_gen::{elevation_noise_fn, get_seamless_point};
+use super::random::{hash_coords, SeededRng};
+
+/// Maximum number of tiles a river can flow before it is cut off
+pub const RIVER_MAX_LENGTH: i64 = 4 * (MAP_CHUNK_TILES_LENGTH as i64);
+/// Rivers only spring from tiles at or above this elevation noise value
+pub const RIVER_SOURCE_MIN_ELEVATION: f64 = 0.1;
+/// Maximum number of tiles in the lake pooled at the end of a river
+pub const RIVER_LAKE_MAX_TILES: usize = 24;
+
+/// Traces the center lines of all rivers (and the lakes they pool into) that flow through a map.
+///
+/// Every chunk in the world rolls, using only `source_seed` and its chunk coordinate, whether it
+/// contains a river source. From each source, the river follows the steepest descent of the
+/// elevation noise until it flows off the world's origin edge, pools into a lake at a local
+/// minimum, or reaches `RIVER_MAX_LENGTH`. Sources from chunks beyond the map bounds are also
+/// traced, so rivers flowing into the map from outside are included.
+///
+/// Tiles are returned in world tile coordinates and may lie outside of the map. Since a river only
+/// depends on the seeds and world coordinates, a chunk always contains the same river tiles
+/// regardless of the size of the map or the order chunks are generated in.
+///
+/// When wrapping, the elevation is the seamless elevation of the map (see:
+/// `effigy_gen::noise_gen::generate_elevation_noise`), only chunks of the map roll sources, and
+/// rivers flow across the map edges onto the opposite side instead of off the world. Tiles are
+/// then always returned within the map.
+pub fn trace_river_tiles(
+    elevation_seed: u32,
+    source_seed: u32,
+    density: f64,
+    map_chunks_count: &MapChunkXY,
+    is_wrapping: bool,
+) -> Vec<(i64, i64)> {
+    assert!(
+        (0.0..=1.0).contains(&density),
+        "Density value needs to be between 0.0 and 1.0 (inclusive)"
+    );
+
+    let chunk_length = MAP_CHUNK_TILES_LENGTH as i64;
+    let width = map_chunks_count.x as i64 * chunk_length;
+    let height = map_chunks_count.y as i64 * chunk_length;
+    let elevation_noise = elevation_noise_fn(elevation_seed);
+    let get_elevation = |x: i64, y: i64| -> f64 {
+        if is_wrapping {
+            elevation_noise.get(get_seamless_point(
+                x.rem_euclid(width) as usize,
+                y.rem_euclid(height) as usize,
+                width as usize,
+                height as usize,
+                (0.0, map_chunks_count.x as f64),
+                (0.0, map_chunks_count.y as f64),
+            ))
+        } else {
+            elevation_noise.get([
+                x as f64 / chunk_length as f64,
+                y as f64 / chunk_length as f64,
+            ])
+        }
+    };
+
+    // Rivers can only flow into the map from chunks within reach of its far edges
+    let margin_chunks = if is_wrapping {
+        0
+    } else {
+        (RIVER_MAX_LENGTH + chunk_length - 1) / chunk_length + 1
+    };
+
+    let mut river_tiles: Vec<(i64, i64)> = Vec::new();
+    for chunk_y in 0..(map_chunks_count.y as i64 + margin_chunks) {
+        for chunk_x in 0..(map_chunks_count.x as i64 + margin_chunks) {
+            let mut rng = SeededRng::new(hash_coords(source_seed, chunk_x, chunk_y));
+            if rng.next_f64() >= density {
+                continue;
+            }
+            let source = (
+                chunk_x * chunk_length + rng.next_below(MAP_CHUNK_TILES_LENGTH as u32) as i64,
+                chunk_y * chunk_length + rng.next_below(MAP_CHUNK_TILES_LENGTH as u32) as i64,
+            );
+            if get_elevation(source.0, source.1) < RIVER_SOURCE_MIN_ELEVATION {
+                continue;
+            }
+            trace!("Tracing river from source {:?}", source);
+            trace_river(&get_elevation, source, is_wrapping, &mut river_tiles);
+        }
+    }
+    if is_wrapping {
+        for (x, y) in river_tiles.iter_mut() {
+            *x = x.rem_euclid(width);
+            *y = y.rem_euclid(height);
+        }
+    }
+    river_tiles
+}
+
+fn trace_river(
+    get_elevation: &impl Fn(i64, i64) -> f64,
+    source: (i64, i64),
+    is_wrapping: bool,
+    river_tiles: &mut Vec<(i64, i64)>,
+) {
+    let (mut x, mut y) = source;
+    let mut elevation = get_elevation(x, y);
+    for _ in 0..RIVER_MAX_LENGTH {
+        // The river flows off the origin edges of the world
+        if !is_wrapping && (x < 0 || y < 0) {
+            return;
+        }
+        river_tiles.push((x, y));
+
+        // Neighbors are checked in a fixed order so ties always resolve the same way
+        let lowest_neighbor = [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+            .into_iter()
+            .map(|(nx, ny)| (nx, ny, get_elevation(nx, ny)))
+            .fold(
+                None,
+                |lowest: Option<(i64, i64, f64)>, neighbor| match lowest {
+                    Some(lowest) if lowest.2 <= neighbor.2 => Some(lowest),
+                    _ => Some(neighbor),
+                },
+            )
+            .unwrap();
+
+        if lowest_neighbor.2 >= elevation {
+            pool_lake(get_elevation, (x, y), is_wrapping, river_tiles);
+            return;
+        }
+        (x, y, elevation) = lowest_neighbor;
+    }
+}
+
+/// Floods outwards from a local minimum, always taking the lowest tile on the shoreline next
+fn pool_lake(
+    get_elevation: &impl Fn(i64, i64) -> f64,
+    origin: (i64, i64),
+    is_wrapping: bool,
+    river_tiles: &mut Vec<(i64, i64)>,
+) {
+    let mut visited: HashSet<(i64, i64)> = HashSet::from([origin]);
+    let mut shoreline: Vec<(i64, i64, f64)> = Vec::new();
+    let mut current = origin;
+    for _ in 1..RIVER_LAKE_MAX_TILES {
+        let (x, y) = current;
+        for (nx, ny) in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+            if (is_wrapping || (nx >= 0 && ny >= 0)) && visited.insert((nx, ny)) {
+                shoreline.push((nx, ny, get_elevation(nx, ny)));
+            }
+        }
+
+        let lowest_index = match shoreline
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.2.total_cmp(&b.2))
+        {
+            Some((index, _)) => index,
+            None => return,
+        };
+        let (lx, ly, _) = shoreline.remove(lowest_index);
+        river_tiles.push((lx, ly));
+        current = (lx, ly);
+    }
+}
@@ -0,0 +1,94 @@
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+/// Birth and survival table of a cellular automaton run over a layer mask, indexed by how many of
+/// the 8 surrounding tiles are in the mask.
+/// See: `MapGenerator::with_smoothing`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmoothingRule {
+    /// Whether a tile outside the mask joins it, per count of neighbors in the mask
+    pub birth: [bool; 9],
+    /// Whether a tile in the mask stays in it, per count of neighbors in the mask
+    pub survival: [bool; 9],
+    /// Number of times the rule is applied to the whole mask
+    pub iterations: u32,
+}
+impl SmoothingRule {
+    /// A tile joins the mask with at least `birth_min` neighbors in the mask, and stays in it with
+    /// at least `survival_min`
+    pub const fn from_thresholds(birth_min: usize, survival_min: usize, iterations: u32) -> Self {
+        let mut birth = [false; 9];
+        let mut survival = [false; 9];
+        let mut count = 0;
+        while count < 9 {
+            birth[count] = count >= birth_min;
+            survival[count] = count >= survival_min;
+            count += 1;
+        }
+        SmoothingRule {
+            birth,
+            survival,
+            iterations,
+        }
+    }
+
+    /// The common "4-5 rule", which removes specks and fills holes while keeping the overall shape:
+    /// a tile joins the mask with at least 5 neighbors in it, and stays with at least 4
+    pub const fn four_five(iterations: u32) -> Self {
+        SmoothingRule::from_thresholds(5, 4, iterations)
+    }
+}
+
+/// Applies the rule to a row-major mask of the whole map, returning the smoothed mask.
+///
+/// Every iteration reads only the previous iteration, so the result never depends on scan order,
+/// and chunk borders are treated like any other tile. Neighbors beyond the edges of the map repeat
+/// the nearest edge tile, so masks aren't pulled towards or away from the map edges, or when
+/// wrapping, are the tiles on the opposite edge.
+pub fn smooth_mask(
+    mask: &[bool],
+    width: usize,
+    height: usize,
+    rule: &SmoothingRule,
+    is_wrapping: bool,
+) -> Vec<bool> {
+    assert_eq!(
+        mask.len(),
+        width * height,
+        "Expected a mask value per tile of the map"
+    );
+
+    let get_neighbors = |value: usize, length: usize| -> [usize; 3] {
+        if is_wrapping {
+            [(value + length - 1) % length, value, (value + 1) % length]
+        } else {
+            [value.saturating_sub(1), value, (value + 1).min(length - 1)]
+        }
+    };
+
+    let mut current = mask.to_vec();
+    let mut next = vec![false; width * height];
+    for _ in 0..rule.iterations {
+        for y in 0..height {
+            for x in 0..width {
+                let mut count: usize = 0;
+                for neighbor_y in get_neighbors(y, height) {
+                    for neighbor_x in get_neighbors(x, width) {
+                        if current[neighbor_y * width + neighbor_x] {
+                            count += 1;
+                        }
+                    }
+                }
+                let index = y * width + x;
+                next[index] = if current[index] {
+                    // The center tile was counted as one of its own neighbors
+                    rule.survival[count - 1]
+                } else {
+                    rule.birth[count]
+                };
+            }
+        }
+        std::mem::swap(&mut current, &mut next);
+    }
+    current
+}
@@ -0,0 +1,93 @@
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use effigy_shared::game::map::{MapChunkXY, MapGrid, MapTileVisualType, MapTileXY};
+
+use super::map_gen::MapGenerator;
+use super::smoothing::{smooth_mask, SmoothingRule};
+
+fn parse_mask(rows: &str) -> Vec<bool> {
+    rows.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c == '#')
+        .collect()
+}
+
+#[test]
+fn four_five_rule_removes_specks_and_holes() {
+    let mask = parse_mask(
+        "....####
+         .#..####
+         ....####
+         ....####
+         ....####
+         ....#.##
+         ....####
+         ....####",
+    );
+    let expected = parse_mask(
+        "....####
+         ....####
+         ....####
+         ....####
+         ....####
+         ....####
+         ....####
+         ....####",
+    );
+    let smoothed = smooth_mask(&mask, 8, 8, &SmoothingRule::four_five(1), false);
+    assert_eq!(smoothed, expected);
+    // Already smooth masks are left as they are
+    assert_eq!(
+        smooth_mask(&smoothed, 8, 8, &SmoothingRule::four_five(3), false),
+        expected
+    );
+}
+
+fn count_isolated_tiles(grid: &MapGrid, visual_type: &MapTileVisualType) -> usize {
+    let bounds = grid.get_tiles_bounds();
+    let is_type = |x: i64, y: i64| {
+        x >= 0
+            && y >= 0
+            && grid
+                .get_tile(&MapTileXY {
+                    x: x as _,
+                    y: y as _,
+                })
+                .is_some_and(|tile| tile.visual_type == *visual_type)
+    };
+    let mut count: usize = 0;
+    for y in 0..bounds.y as i64 {
+        for x in 0..bounds.x as i64 {
+            if is_type(x, y)
+                && !is_type(x - 1, y)
+                && !is_type(x + 1, y)
+                && !is_type(x, y - 1)
+                && !is_type(x, y + 1)
+            {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+#[test]
+fn generate_with_smoothing() {
+    let seed: u32 = 453537;
+    let map_chunks_size = MapChunkXY { x: 4, y: 4 };
+    let generator = || MapGenerator::new(seed, &map_chunks_size);
+
+    let grid = generator().with_trees(0.4, 0.5).generate();
+    let isolated_count = count_isolated_tiles(&grid, &MapTileVisualType::LandTree);
+    assert!(isolated_count > 0);
+
+    let smoothed_grid = generator()
+        .with_smoothing(SmoothingRule::four_five(2))
+        .with_trees(0.4, 0.5)
+        .generate();
+    // Tiles can still be left isolated where their diagonal neighbors hold them in place
+    assert!(
+        count_isolated_tiles(&smoothed_grid, &MapTileVisualType::LandTree) < isolated_count / 10
+    );
+}
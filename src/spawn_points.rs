@@ -0,0 +1,202 @@
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use std::collections::VecDeque;
+
+use effigy_shared::game::map::{MapGrid, MapTileVisualType, MapTileXY};
+
+use super::grid_index::{get_neighbor_indices, get_tile_at_index, get_tile_xy_at_index};
+use super::random::hash_coords;
+use super::regions::{label_map_regions, RegionConnectivity};
+
+/// A selected spawn tile and how it compares to the other spawns
+#[derive(Debug)]
+pub struct SpawnPoint {
+    pub tile: MapTileXY,
+    /// Tile distance to the nearest water, or `None` if the map has no water
+    pub water_distance: Option<u32>,
+    /// Tile distance to the nearest building, or `None` if the map has no buildings
+    pub building_distance: Option<u32>,
+    /// How closely this spawn matches the average of all selected spawns, in `0.0..=1.0`, where
+    /// `1.0` means its distances to water and buildings are exactly the average
+    pub fairness: f64,
+}
+
+/// Deterministically selects `count` spawn tiles on walkable land for a multiplayer match.
+///
+/// Spawns are all chosen from the largest walkable region, so every spawn can reach every other.
+/// Each spawn is at least `min_distance` tiles (straight-line) from the others, and spawns whose
+/// distances to water and buildings are closest to the typical tile of the region are preferred,
+/// so no player starts notably closer to resources. Remaining ties are broken by hashing `seed`
+/// with the tile position.
+///
+/// Returns `None` if fewer than `count` spawns can satisfy `min_distance`.
+pub fn select_spawn_points(
+    map_grid: &MapGrid,
+    seed: u32,
+    count: usize,
+    min_distance: u32,
+) -> Option<Vec<SpawnPoint>> {
+    info!(
+        "Selecting spawn points: count={} and min_distance={} ...",
+        count, min_distance
+    );
+    let func_start_time = std::time::Instant::now();
+
+    let width = map_grid.get_tiles_bounds().x as usize;
+    let height = map_grid.get_tiles_bounds().y as usize;
+
+    let regions = label_map_regions(map_grid, RegionConnectivity::Walkability);
+    // Largest walkable land region, preferring the first labeled on ties
+    let spawn_region = regions
+        .get_regions()
+        .iter()
+        .filter(|region| region.visual_type == MapTileVisualType::Land)
+        .reduce(|largest, region| {
+            if region.area > largest.area {
+                region
+            } else {
+                largest
+            }
+        })?;
+    let candidate_indices: Vec<usize> = regions
+        .get_region_tile_indices(spawn_region.id)
+        .iter()
+        .copied()
+        .filter(|&index| {
+            get_tile_at_index(map_grid, width, index).visual_type == MapTileVisualType::Land
+        })
+        .collect();
+
+    let water_distances =
+        get_distances_to_visual_type(map_grid, width, height, &MapTileVisualType::Water);
+    let building_distances =
+        get_distances_to_visual_type(map_grid, width, height, &MapTileVisualType::Building);
+
+    // Prefer tiles nearest the median distances, which most spawns can match
+    let median_water_distance = get_median_distance(&water_distances, &candidate_indices);
+    let median_building_distance = get_median_distance(&building_distances, &candidate_indices);
+    let mut ranked_indices: Vec<(u32, u64, usize)> = candidate_indices
+        .iter()
+        .map(|&index| {
+            let deviation = get_distance_deviation(water_distances[index], median_water_distance)
+                + get_distance_deviation(building_distances[index], median_building_distance);
+            let tile_xy = get_tile_xy_at_index(width, index);
+            let tiebreak = hash_coords(seed, tile_xy.x as i64, tile_xy.y as i64);
+            (deviation, tiebreak, index)
+        })
+        .collect();
+    ranked_indices.sort_unstable();
+
+    let min_distance_squared = (min_distance as u64) * (min_distance as u64);
+    let mut spawn_indices: Vec<usize> = Vec::with_capacity(count);
+    for &(_, _, index) in &ranked_indices {
+        if spawn_indices.len() == count {
+            break;
+        }
+        let is_far_enough = spawn_indices.iter().all(|&spawn_index| {
+            let dx = (index % width).abs_diff(spawn_index % width) as u64;
+            let dy = (index / width).abs_diff(spawn_index / width) as u64;
+            dx * dx + dy * dy >= min_distance_squared
+        });
+        if is_far_enough {
+            spawn_indices.push(index);
+        }
+    }
+    if spawn_indices.len() < count {
+        warn!(
+            "... only found {} of {} spawn points at least {} tiles apart",
+            spawn_indices.len(),
+            count,
+            min_distance
+        );
+        return None;
+    }
+
+    let mean_water_distance = get_mean_distance(&water_distances, &spawn_indices);
+    let mean_building_distance = get_mean_distance(&building_distances, &spawn_indices);
+    let spawn_points = spawn_indices
+        .into_iter()
+        .map(|index| {
+            let deviation = mean_water_distance
+                .zip(water_distances[index])
+                .map_or(0.0, |(mean, distance)| (distance as f64 - mean).abs())
+                + mean_building_distance
+                    .zip(building_distances[index])
+                    .map_or(0.0, |(mean, distance)| (distance as f64 - mean).abs());
+            SpawnPoint {
+                tile: get_tile_xy_at_index(width, index),
+                water_distance: water_distances[index],
+                building_distance: building_distances[index],
+                fairness: 1.0 / (1.0 + deviation),
+            }
+        })
+        .collect();
+
+    info!(
+        "... done selecting spawn points! Completed in {:.3} seconds",
+        func_start_time.elapsed().as_secs_f32()
+    );
+    Some(spawn_points)
+}
+
+/// Breadth-first tile distance from every tile to the nearest tile of the visual type, ignoring
+/// walkability. All `None` if the map has no tiles of the visual type.
+fn get_distances_to_visual_type(
+    map_grid: &MapGrid,
+    width: usize,
+    height: usize,
+    visual_type: &MapTileVisualType,
+) -> Vec<Option<u32>> {
+    let mut distances: Vec<Option<u32>> = vec![None; width * height];
+    let mut queue: VecDeque<usize> = VecDeque::new();
+    for (index, distance) in distances.iter_mut().enumerate() {
+        if get_tile_at_index(map_grid, width, index).visual_type == *visual_type {
+            *distance = Some(0);
+            queue.push_back(index);
+        }
+    }
+    while let Some(index) = queue.pop_front() {
+        let distance = distances[index].unwrap();
+        for neighbor_index in get_neighbor_indices(width, height, index) {
+            if distances[neighbor_index].is_none() {
+                distances[neighbor_index] = Some(distance + 1);
+                queue.push_back(neighbor_index);
+            }
+        }
+    }
+    distances
+}
+
+fn get_median_distance(distances: &[Option<u32>], indices: &[usize]) -> Option<u32> {
+    let mut sorted_distances: Vec<u32> = indices
+        .iter()
+        .filter_map(|&index| distances[index])
+        .collect();
+    sorted_distances.sort_unstable();
+    sorted_distances.get(sorted_distances.len() / 2).copied()
+}
+
+fn get_mean_distance(distances: &[Option<u32>], indices: &[usize]) -> Option<f64> {
+    let known_distances: Vec<u32> = indices
+        .iter()
+        .filter_map(|&index| distances[index])
+        .collect();
+    if known_distances.is_empty() {
+        return None;
+    }
+    Some(
+        known_distances
+            .iter()
+            .map(|&distance| distance as f64)
+            .sum::<f64>()
+            / known_distances.len() as f64,
+    )
+}
+
+fn get_distance_deviation(distance: Option<u32>, median_distance: Option<u32>) -> u32 {
+    match (distance, median_distance) {
+        (Some(distance), Some(median_distance)) => distance.abs_diff(median_distance),
+        _ => 0,
+    }
+}
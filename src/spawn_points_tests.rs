@@ -0,0 +1,59 @@
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use effigy_shared::game::map::{MapChunkXY, MapGrid, MapTileVisualType};
+
+use super::map_gen::MapGenerator;
+use super::pathfinding::{find_path, MovementCosts};
+use super::spawn_points::select_spawn_points;
+
+fn generate_spawn_map_grid() -> MapGrid {
+    MapGenerator::new(453537, &MapChunkXY { x: 4, y: 4 })
+        .with_terrain(0.2, 0.5)
+        .with_buildings(0.1, 0.25)
+        .with_water(0.2, 0.5)
+        .with_trees(0.2, 0.5)
+        .generate()
+}
+
+#[test]
+fn select_fair_spawn_points() {
+    let grid = generate_spawn_map_grid();
+    let min_distance: u32 = 24;
+    let spawn_points = select_spawn_points(&grid, 1234, 4, min_distance).unwrap();
+    assert_eq!(spawn_points.len(), 4);
+
+    for (i, spawn_point) in spawn_points.iter().enumerate() {
+        assert_eq!(
+            grid.get_tile(&spawn_point.tile).unwrap().visual_type,
+            MapTileVisualType::Land
+        );
+        assert!((0.0..=1.0).contains(&spawn_point.fairness));
+
+        for other_spawn_point in &spawn_points[(i + 1)..] {
+            let dx = spawn_point.tile.x.abs_diff(other_spawn_point.tile.x) as u64;
+            let dy = spawn_point.tile.y.abs_diff(other_spawn_point.tile.y) as u64;
+            assert!(dx * dx + dy * dy >= (min_distance as u64) * (min_distance as u64));
+            assert!(find_path(
+                &grid,
+                &spawn_point.tile,
+                &other_spawn_point.tile,
+                &MovementCosts::default()
+            )
+            .is_some());
+        }
+    }
+
+    // The same map and seed always select the same spawns
+    let repeated_spawn_points = select_spawn_points(&grid, 1234, 4, min_distance).unwrap();
+    for (spawn_point, repeated_spawn_point) in spawn_points.iter().zip(&repeated_spawn_points) {
+        assert_eq!(spawn_point.tile, repeated_spawn_point.tile);
+    }
+}
+
+#[test]
+fn select_too_many_spawn_points() {
+    let grid = generate_spawn_map_grid();
+    let too_far = grid.get_tiles_bounds().x * 2;
+    assert!(select_spawn_points(&grid, 1234, 2, too_far).is_none());
+}
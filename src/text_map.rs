@@ -0,0 +1,297 @@
+//! Renders a `MapGrid` as text, with a character per tile, and parses the same format back, so
+//! tests and logs can show small maps inline. Tiles are written as `.` land, `T` tree, `~` water,
+//! `#` terrain and `B` building.
+//!
+//! Rows are written from the lowest y, like the rows of a `Prefab`. With walls, every tile is
+//! surrounded by its edges: a box-drawing wall where both tiles are blocked from crossing, a space
+//! where the edge is open, or an arrow pointing the only way units can cross a one-way edge. Outer
+//! edges of the region are walls where the tile's side is blocked.
+
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use std::fmt;
+
+use effigy_shared::game::map::{
+    MapChunkXY, MapGrid, MapTileIndex, MapTileXY, SquareBounds, MAP_CHUNK_TILES_LENGTH,
+};
+
+use super::grid_index::{get_tile_char, get_visual_type_from_char, NO_BLOCKED_EDGES};
+use super::map_gen::simplify_map_walkability_blockage;
+use super::walkability::WalkabilityRules;
+
+const VERTICAL_WALL: char = '│';
+const HORIZONTAL_WALL: char = '─';
+/// Corners by whether a wall leaves them towards the previous row, the next row, the previous
+/// column and the next column, in the bits of the index from highest to lowest
+const CORNERS: [char; 16] = [
+    ' ', '╶', '╴', '─', '╷', '┌', '┐', '┬', '╵', '└', '┘', '┴', '│', '├', '┤', '┼',
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MapTextError {
+    Empty,
+    /// A line is a different length than the first, counting characters
+    UnevenLine {
+        line: usize,
+    },
+    UnknownCharacter {
+        line: usize,
+        column: usize,
+        character: char,
+    },
+}
+impl fmt::Display for MapTextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapTextError::Empty => write!(f, "map text has no tiles"),
+            MapTextError::UnevenLine { line } => {
+                write!(f, "line {} of map text is a different length", line)
+            }
+            MapTextError::UnknownCharacter {
+                line,
+                column,
+                character,
+            } => write!(
+                f,
+                "unknown character '{}' at line {}, column {} of map text",
+                character, line, column
+            ),
+        }
+    }
+}
+impl std::error::Error for MapTextError {}
+
+/// Renders the whole map. See: `render_map_text_region`
+pub fn render_map_text(map_grid: &MapGrid, with_walls: bool) -> String {
+    let tiles_bounds = map_grid.get_tiles_bounds();
+    render_map_text_region(map_grid, 0, 0, tiles_bounds.x, tiles_bounds.y, with_walls)
+}
+
+/// Renders the tiles from (`x`, `y`) up to but excluding (`x + width`, `y + height`), a line per
+/// row ending in a newline
+pub fn render_map_text_region(
+    map_grid: &MapGrid,
+    x: MapTileIndex,
+    y: MapTileIndex,
+    width: MapTileIndex,
+    height: MapTileIndex,
+    with_walls: bool,
+) -> String {
+    let tiles_bounds = map_grid.get_tiles_bounds();
+    assert!(
+        width > 0 && height > 0 && x + width <= tiles_bounds.x && y + height <= tiles_bounds.y,
+        "Region of {}x{} tiles at ({}, {}) isn't within the map",
+        width,
+        height,
+        x,
+        y
+    );
+
+    let get_tile = |region_x: MapTileIndex, region_y: MapTileIndex| {
+        map_grid
+            .get_tile(&MapTileXY {
+                x: x + region_x,
+                y: y + region_y,
+            })
+            .unwrap()
+    };
+    if !with_walls {
+        let mut text = String::with_capacity(((width + 1) * height) as usize);
+        for region_y in 0..height {
+            for region_x in 0..width {
+                text.push(get_tile_char(&get_tile(region_x, region_y).visual_type));
+            }
+            text.push('\n');
+        }
+        return text;
+    }
+
+    // Edge characters first, as the corners depend on them
+    let text_width = (2 * width + 1) as usize;
+    let text_height = (2 * height + 1) as usize;
+    let mut lines: Vec<Vec<char>> = vec![vec![' '; text_width]; text_height];
+    for region_y in 0..height {
+        for region_x in 0..width {
+            let tile = get_tile(region_x, region_y);
+            let (column, line) = (2 * region_x as usize + 1, 2 * region_y as usize + 1);
+            lines[line][column] = get_tile_char(&tile.visual_type);
+
+            let blocked = &tile.walkability_blocked;
+            lines[line][column + 1] = if region_x + 1 < width {
+                let next_blocked = &get_tile(region_x + 1, region_y).walkability_blocked;
+                get_edge_char(blocked.right, next_blocked.left, VERTICAL_WALL, '>', '<')
+            } else {
+                get_edge_char(blocked.right, true, VERTICAL_WALL, ' ', ' ')
+            };
+            lines[line + 1][column] = if region_y + 1 < height {
+                let next_blocked = &get_tile(region_x, region_y + 1).walkability_blocked;
+                get_edge_char(blocked.up, next_blocked.down, HORIZONTAL_WALL, 'v', '^')
+            } else {
+                get_edge_char(blocked.up, true, HORIZONTAL_WALL, ' ', ' ')
+            };
+            if region_x == 0 {
+                lines[line][0] = get_edge_char(blocked.left, true, VERTICAL_WALL, ' ', ' ');
+            }
+            if region_y == 0 {
+                lines[0][column] = get_edge_char(blocked.down, true, HORIZONTAL_WALL, ' ', ' ');
+            }
+        }
+    }
+    for line in (0..text_height).step_by(2) {
+        for column in (0..text_width).step_by(2) {
+            let is_wall =
+                |line: Option<usize>, column: Option<usize>, wall: char| match (line, column) {
+                    (Some(line), Some(column)) if line < text_height && column < text_width => {
+                        lines[line][column] == wall
+                    }
+                    _ => false,
+                };
+            let is_previous_line_wall = is_wall(line.checked_sub(1), Some(column), VERTICAL_WALL);
+            let is_next_line_wall = is_wall(Some(line + 1), Some(column), VERTICAL_WALL);
+            let is_previous_column_wall =
+                is_wall(Some(line), column.checked_sub(1), HORIZONTAL_WALL);
+            let is_next_column_wall = is_wall(Some(line), Some(column + 1), HORIZONTAL_WALL);
+            let corner_index = ((is_previous_line_wall as usize) << 3)
+                | ((is_next_line_wall as usize) << 2)
+                | ((is_previous_column_wall as usize) << 1)
+                | (is_next_column_wall as usize);
+            lines[line][column] = CORNERS[corner_index];
+        }
+    }
+
+    let mut text = String::with_capacity(text_width * 3 * text_height);
+    for line in lines {
+        text.extend(line);
+        text.push('\n');
+    }
+    text
+}
+
+/// Character of the edge between a tile and the next tile along a row or column, given whether
+/// each tile's side of the edge is blocked
+const fn get_edge_char(
+    is_blocked: bool,
+    is_next_blocked: bool,
+    wall: char,
+    forward: char,
+    backward: char,
+) -> char {
+    match (is_blocked, is_next_blocked) {
+        (true, true) => wall,
+        (false, false) => ' ',
+        (false, true) => forward,
+        (true, false) => backward,
+    }
+}
+
+/// Parses text in the format of `render_map_text` into the lowest x and y tiles of a map of as few
+/// chunks as fit it. The rest of the map is land without blocked edges.
+///
+/// Without walls, walkability is decided by the default `WalkabilityRules` like for a generated
+/// map, empty lines before and after the map are ignored, and every line needs to be the same
+/// length. With walls, the blocked sides of each tile are read from its edges. As the outer edges
+/// can be all spaces, only a single newline before the map is ignored, like the one starting a
+/// multi-line string literal, and short or missing lines are padded with spaces, as editors may
+/// strip them.
+pub fn parse_map_text(text: &str, with_walls: bool) -> Result<MapGrid, MapTextError> {
+    let lines: Vec<Vec<char>> = if with_walls {
+        text.strip_prefix('\n')
+            .unwrap_or(text)
+            .lines()
+            .map(|line| line.chars().collect())
+            .collect()
+    } else {
+        let lines: Vec<Vec<char>> = text
+            .lines()
+            .map(|line| line.chars().collect())
+            .skip_while(|line: &Vec<char>| line.iter().all(|character| character.is_whitespace()))
+            .collect();
+        let lines_count = lines.len()
+            - lines
+                .iter()
+                .rev()
+                .take_while(|line| line.iter().all(|character| character.is_whitespace()))
+                .count();
+        lines[..lines_count].to_vec()
+    };
+    let lines = &lines[..];
+    if lines.is_empty() {
+        return Err(MapTextError::Empty);
+    }
+
+    let (width, height) = if with_walls {
+        let text_width = lines.iter().map(|line| line.len()).max().unwrap();
+        (text_width.max(2) / 2, lines.len() / 2)
+    } else {
+        (lines[0].len(), lines.len())
+    };
+    if width == 0 || height == 0 {
+        return Err(MapTextError::Empty);
+    }
+    let chunk_length = MAP_CHUNK_TILES_LENGTH as usize;
+    let mut map_grid = MapGrid::new(&MapChunkXY {
+        x: width.div_ceil(chunk_length) as _,
+        y: height.div_ceil(chunk_length) as _,
+    });
+
+    let get_char = |line: usize, column: usize| -> char {
+        lines
+            .get(line)
+            .and_then(|line| line.get(column))
+            .copied()
+            .unwrap_or(' ')
+    };
+    for (line_index, line) in lines.iter().enumerate() {
+        if !with_walls && line.len() != width {
+            return Err(MapTextError::UnevenLine {
+                line: line_index + 1,
+            });
+        }
+    }
+    for y in 0..height {
+        for x in 0..width {
+            let (line, column) = if with_walls {
+                (2 * y + 1, 2 * x + 1)
+            } else {
+                (y, x)
+            };
+            let character = get_char(line, column);
+            let visual_type =
+                get_visual_type_from_char(character).ok_or(MapTextError::UnknownCharacter {
+                    line: line + 1,
+                    column: column + 1,
+                    character,
+                })?;
+            let walkability_blocked = if with_walls {
+                SquareBounds {
+                    left: is_side_blocked(get_char(line, column - 1), VERTICAL_WALL, '>'),
+                    right: is_side_blocked(get_char(line, column + 1), VERTICAL_WALL, '<'),
+                    down: is_side_blocked(get_char(line - 1, column), HORIZONTAL_WALL, 'v'),
+                    up: is_side_blocked(get_char(line + 1, column), HORIZONTAL_WALL, '^'),
+                }
+            } else {
+                NO_BLOCKED_EDGES
+            };
+            map_grid
+                .get_tile_mut(&MapTileXY {
+                    x: x as MapTileIndex,
+                    y: y as MapTileIndex,
+                })
+                .unwrap()
+                .set_visual_type(&visual_type)
+                .set_walkability_blocked(&walkability_blocked);
+        }
+    }
+
+    if !with_walls {
+        simplify_map_walkability_blockage(&mut map_grid, &WalkabilityRules::default(), false);
+    }
+    Ok(map_grid)
+}
+
+/// Whether a tile's side is blocked, given the edge character beside it and the arrow pointing
+/// into the tile, as units can only cross a one-way edge the way its arrow points
+const fn is_side_blocked(edge: char, wall: char, arrow_into_tile: char) -> bool {
+    edge == wall || edge == arrow_into_tile
+}
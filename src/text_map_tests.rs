@@ -0,0 +1,150 @@
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use effigy_shared::game::map::{MapChunkXY, MapTileVisualType, MapTileXY};
+
+use super::map_gen::MapGenerator;
+use super::text_map::{parse_map_text, render_map_text, render_map_text_region, MapTextError};
+
+#[test]
+fn render_and_parse_text_map() {
+    let text = "
+.TT~~
+..#~B
+.....
+";
+    let grid = parse_map_text(text, false).unwrap();
+    assert_eq!(grid.get_chunks_bounds(), MapChunkXY { x: 1, y: 1 });
+    assert_eq!(
+        grid.get_tile(&MapTileXY { x: 1, y: 0 })
+            .unwrap()
+            .visual_type,
+        MapTileVisualType::LandTree
+    );
+    assert_eq!(
+        grid.get_tile(&MapTileXY { x: 4, y: 1 })
+            .unwrap()
+            .visual_type,
+        MapTileVisualType::Building
+    );
+    assert_eq!(
+        render_map_text_region(&grid, 0, 0, 5, 3, false),
+        text.trim_start()
+    );
+
+    // Walls between different tile types, and along the outer edges of the map
+    let walls_text = "
+┌─┬───┬───┐
+│.│T T│~ ~│
+│ └─┬─┤ ┌─┤
+│. .│#│~│B│
+╵   └─┴─┴─┘
+";
+    assert_eq!(
+        render_map_text_region(&grid, 0, 0, 5, 2, true),
+        walls_text.trim_start()
+    );
+
+    assert_eq!(
+        parse_map_text(".T\n.X\n", false).err(),
+        Some(MapTextError::UnknownCharacter {
+            line: 2,
+            column: 2,
+            character: 'X'
+        })
+    );
+    assert_eq!(
+        parse_map_text(".T\n.\n", false).err(),
+        Some(MapTextError::UnevenLine { line: 2 })
+    );
+}
+
+#[test]
+fn parse_one_way_walls() {
+    // Units can step down from the tree into the land, but not back
+    let text = "
+┌───┐
+│T>.│
+└───┘
+";
+    let grid = parse_map_text(text, true).unwrap();
+    let tree = &grid
+        .get_tile(&MapTileXY { x: 0, y: 0 })
+        .unwrap()
+        .walkability_blocked;
+    let land = &grid
+        .get_tile(&MapTileXY { x: 1, y: 0 })
+        .unwrap()
+        .walkability_blocked;
+    assert!(!tree.right && land.left);
+    assert!(tree.left && tree.up && tree.down && land.right);
+    assert_eq!(
+        render_map_text_region(&grid, 0, 0, 2, 1, true),
+        text.trim_start()
+    );
+}
+
+#[test]
+fn text_map_round_trip() {
+    let grid = MapGenerator::new(453537, &MapChunkXY { x: 1, y: 1 })
+        .with_terrain(0.2, 0.5)
+        .with_water(0.2, 0.5)
+        .with_trees(0.2, 0.5)
+        .generate();
+    let text = render_map_text(&grid, true);
+    let parsed = parse_map_text(&text, true).unwrap();
+    assert_eq!(render_map_text(&parsed, true), text);
+
+    let tiles_bounds = grid.get_tiles_bounds();
+    for y in 0..tiles_bounds.y {
+        for x in 0..tiles_bounds.x {
+            let tile = grid.get_tile(&MapTileXY { x, y }).unwrap();
+            let parsed_tile = parsed.get_tile(&MapTileXY { x, y }).unwrap();
+            assert_eq!(tile.visual_type, parsed_tile.visual_type);
+            assert_eq!(tile.walkability_blocked, parsed_tile.walkability_blocked);
+        }
+    }
+}
+
+#[test]
+fn text_map_region_round_trip_with_open_borders() {
+    let grid = parse_map_text(
+        "
+.......
+.......
+...~~..
+.......
+.......
+",
+        false,
+    )
+    .unwrap();
+    // Every outer edge of the region is open, so its first and last lines are all spaces
+    let text = render_map_text_region(&grid, 1, 1, 5, 3, true);
+    let expected_text = "           
+ . . . . . 
+    ┌───┐  
+ . .│~ ~│. 
+    └───┘  
+ . . . . . 
+           
+";
+    assert_eq!(text, expected_text);
+
+    let parsed = parse_map_text(&text, true).unwrap();
+    assert_eq!(render_map_text_region(&parsed, 0, 0, 5, 3, true), text);
+    for y in 0..3 {
+        for x in 0..5 {
+            let tile = grid.get_tile(&MapTileXY { x: x + 1, y: y + 1 }).unwrap();
+            let parsed_tile = parsed.get_tile(&MapTileXY { x, y }).unwrap();
+            assert_eq!(tile.visual_type, parsed_tile.visual_type);
+            assert_eq!(tile.walkability_blocked, parsed_tile.walkability_blocked);
+        }
+    }
+
+    // A lone open tile, with the newline starting a string literal and a stripped bottom border
+    let parsed = parse_map_text("\n   \n . \n", true).unwrap();
+    let tile = parsed.get_tile(&MapTileXY { x: 0, y: 0 }).unwrap();
+    assert_eq!(tile.visual_type, MapTileVisualType::Land);
+    assert!(!tile.walkability_blocked.is_any());
+}
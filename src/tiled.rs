@@ -0,0 +1,349 @@
+//! Exports and imports maps in the JSON map format of the Tiled editor (`.tmj`), so designers can
+//! inspect and tweak generated maps.
+//!
+//! A map has two tile layers, one tile per map tile from the top-left like
+//! `save_map_grid_as_image`:
+//! - `TILES_LAYER_NAME`, using a tileset with a tile per `MapTileVisualType` in declaration order
+//! - `WALKABILITY_LAYER_NAME`, using a tileset with a tile per combination of blocked edges, each
+//!   with a `blocked_left`, `blocked_right`, `blocked_down` and `blocked_up` custom property. A
+//!   blocked side only stops units leaving the tile across it, so an edge blocked on one side is
+//!   one-way.
+//!
+//! The tilesets are embedded in the map, and reference images which aren't written by the
+//! exporter, so Tiled shows placeholders until images with 16 by 16 pixel tiles are provided.
+
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use std::fmt;
+
+use serde_json::{json, Value};
+
+use effigy_shared::game::map::{
+    MapChunkXY, MapGrid, MapTileIndex, MapTileXY, SquareBounds, MAP_CHUNK_TILES_LENGTH,
+};
+
+use super::grid_index::VISUAL_TYPES;
+use super::map_gen::{simplify_map_walkability_blockage, MapGeneratorDescription};
+use super::walkability::WalkabilityRules;
+
+pub const TILES_LAYER_NAME: &str = "tiles";
+pub const WALKABILITY_LAYER_NAME: &str = "walkability";
+const TILES_TILESET_NAME: &str = "effigy_tiles";
+const WALKABILITY_TILESET_NAME: &str = "effigy_walkability";
+
+const TILE_PIXELS: u32 = 16;
+const WALKABILITY_TILES_COUNT: u32 = 16;
+/// Flip and rotation flags Tiled stores in the highest bits of a tile's global ID
+const TILE_FLAGS_MASK: u64 = 0xF000_0000;
+
+#[derive(Debug)]
+pub enum TiledMapError {
+    Json(serde_json::Error),
+    /// A field the map needs is missing, or isn't the expected type, e.g. for infinite maps or
+    /// layers with compressed data
+    InvalidField(&'static str),
+    /// The map isn't a whole number of chunks wide and high
+    UnalignedSize {
+        width: u64,
+        height: u64,
+    },
+    MissingTileset(&'static str),
+    MissingLayer(&'static str),
+    /// A tile of a layer isn't from the layer's tileset
+    InvalidTile {
+        layer: &'static str,
+        x: u64,
+        y: u64,
+    },
+}
+impl fmt::Display for TiledMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TiledMapError::Json(error) => write!(f, "invalid Tiled map JSON: {}", error),
+            TiledMapError::InvalidField(field) => {
+                write!(f, "Tiled map field \"{}\" is missing or invalid", field)
+            }
+            TiledMapError::UnalignedSize { width, height } => write!(
+                f,
+                "Tiled map size {}x{} isn't a multiple of the chunk size {}",
+                width, height, MAP_CHUNK_TILES_LENGTH
+            ),
+            TiledMapError::MissingTileset(name) => {
+                write!(f, "Tiled map has no tileset \"{}\"", name)
+            }
+            TiledMapError::MissingLayer(name) => write!(f, "Tiled map has no layer \"{}\"", name),
+            TiledMapError::InvalidTile { layer, x, y } => write!(
+                f,
+                "tile ({}, {}) of layer \"{}\" isn't from its tileset",
+                x, y, layer
+            ),
+        }
+    }
+}
+impl std::error::Error for TiledMapError {}
+impl From<serde_json::Error> for TiledMapError {
+    fn from(error: serde_json::Error) -> Self {
+        TiledMapError::Json(error)
+    }
+}
+
+/// Writes the map as Tiled map JSON, with the seed and parameters of the generator that produced
+/// it, if given, as map properties
+pub fn export_tiled_map(
+    map_grid: &MapGrid,
+    generator_description: Option<&MapGeneratorDescription>,
+) -> String {
+    let width = map_grid.get_tiles_bounds().x;
+    let height = map_grid.get_tiles_bounds().y;
+    let walkability_first_gid = 1 + VISUAL_TYPES.len() as u32;
+
+    let mut tiles_data: Vec<u32> = Vec::with_capacity(map_grid.get_tiles_count());
+    let mut walkability_data: Vec<u32> = Vec::with_capacity(map_grid.get_tiles_count());
+    for y in 0..height {
+        for x in 0..width {
+            let tile = map_grid.get_tile(&MapTileXY { x, y }).unwrap();
+            let type_index = VISUAL_TYPES
+                .iter()
+                .position(|visual_type| *visual_type == tile.visual_type)
+                .unwrap();
+            tiles_data.push(1 + type_index as u32);
+            walkability_data
+                .push(walkability_first_gid + get_walkability_tile_id(&tile.walkability_blocked));
+        }
+    }
+
+    let tiles_tileset_tiles: Vec<Value> = VISUAL_TYPES
+        .iter()
+        .enumerate()
+        .map(|(id, visual_type)| {
+            json!({
+                "id": id,
+                "properties": [get_property("visual_type", format!("{:?}", visual_type))],
+            })
+        })
+        .collect();
+    let walkability_tileset_tiles: Vec<Value> = (0..WALKABILITY_TILES_COUNT)
+        .map(|id| {
+            let blocked = get_walkability_blocked(id);
+            json!({
+                "id": id,
+                "properties": [
+                    get_property("blocked_down", blocked.down),
+                    get_property("blocked_left", blocked.left),
+                    get_property("blocked_right", blocked.right),
+                    get_property("blocked_up", blocked.up),
+                ],
+            })
+        })
+        .collect();
+
+    let properties: Vec<Value> = match generator_description {
+        Some(description) => vec![
+            get_property("fingerprint", format!("{:016x}", description.fingerprint)),
+            get_property("generator_version", description.version),
+            get_property("seed", description.seed),
+            get_property("steps", description.steps.join("\n")),
+        ],
+        None => Vec::new(),
+    };
+
+    let map = json!({
+        "type": "map",
+        "version": "1.10",
+        "orientation": "orthogonal",
+        "renderorder": "right-down",
+        "infinite": false,
+        "width": width,
+        "height": height,
+        "tilewidth": TILE_PIXELS,
+        "tileheight": TILE_PIXELS,
+        "compressionlevel": -1,
+        "nextlayerid": 3,
+        "nextobjectid": 1,
+        "properties": properties,
+        "layers": [
+            get_tile_layer(1, TILES_LAYER_NAME, width, height, tiles_data),
+            get_tile_layer(2, WALKABILITY_LAYER_NAME, width, height, walkability_data),
+        ],
+        "tilesets": [
+            get_tileset(1, TILES_TILESET_NAME, VISUAL_TYPES.len() as u32, tiles_tileset_tiles),
+            get_tileset(
+                walkability_first_gid,
+                WALKABILITY_TILESET_NAME,
+                WALKABILITY_TILES_COUNT,
+                walkability_tileset_tiles,
+            ),
+        ],
+    });
+    serde_json::to_string_pretty(&map).unwrap()
+}
+
+/// Reads a map written by `export_tiled_map`, possibly edited in Tiled since.
+///
+/// Walkability is read from the walkability layer, unless rules are given, in which case it's
+/// decided by the rules for the tile types like for a generated map, e.g. when tiles were painted
+/// without updating the walkability layer.
+pub fn import_tiled_map(
+    json: &str,
+    rules: Option<&WalkabilityRules>,
+) -> Result<MapGrid, TiledMapError> {
+    let map: Value = serde_json::from_str(json)?;
+    let width = map["width"]
+        .as_u64()
+        .ok_or(TiledMapError::InvalidField("width"))?;
+    let height = map["height"]
+        .as_u64()
+        .ok_or(TiledMapError::InvalidField("height"))?;
+    let chunk_length = MAP_CHUNK_TILES_LENGTH as u64;
+    if width == 0 || height == 0 || width % chunk_length != 0 || height % chunk_length != 0 {
+        return Err(TiledMapError::UnalignedSize { width, height });
+    }
+
+    let tiles_first_gid = get_tileset_first_gid(&map, TILES_TILESET_NAME)?;
+    let tiles_data = get_tile_layer_data(&map, TILES_LAYER_NAME, width * height)?;
+    let mut map_grid = MapGrid::new(&MapChunkXY {
+        x: (width / chunk_length) as _,
+        y: (height / chunk_length) as _,
+    });
+    for (index, &gid) in tiles_data.iter().enumerate() {
+        let (x, y) = (index as u64 % width, index as u64 / width);
+        let visual_type = gid
+            .checked_sub(tiles_first_gid)
+            .and_then(|id| VISUAL_TYPES.get(id as usize))
+            .ok_or(TiledMapError::InvalidTile {
+                layer: TILES_LAYER_NAME,
+                x,
+                y,
+            })?;
+        map_grid
+            .get_tile_mut(&MapTileXY {
+                x: x as MapTileIndex,
+                y: y as MapTileIndex,
+            })
+            .unwrap()
+            .set_visual_type(visual_type);
+    }
+
+    if let Some(rules) = rules {
+        simplify_map_walkability_blockage(&mut map_grid, rules, false);
+        return Ok(map_grid);
+    }
+
+    let walkability_first_gid = get_tileset_first_gid(&map, WALKABILITY_TILESET_NAME)?;
+    let walkability_data = get_tile_layer_data(&map, WALKABILITY_LAYER_NAME, width * height)?;
+    for (index, &gid) in walkability_data.iter().enumerate() {
+        let (x, y) = (index as u64 % width, index as u64 / width);
+        let id = gid
+            .checked_sub(walkability_first_gid)
+            .filter(|&id| id < WALKABILITY_TILES_COUNT as u64)
+            .ok_or(TiledMapError::InvalidTile {
+                layer: WALKABILITY_LAYER_NAME,
+                x,
+                y,
+            })?;
+        map_grid
+            .get_tile_mut(&MapTileXY {
+                x: x as MapTileIndex,
+                y: y as MapTileIndex,
+            })
+            .unwrap()
+            .set_walkability_blocked(&get_walkability_blocked(id as u32));
+    }
+    Ok(map_grid)
+}
+
+/// Tile of the walkability tileset, with a bit per blocked edge
+const fn get_walkability_tile_id(blocked: &SquareBounds) -> u32 {
+    (blocked.left as u32)
+        | ((blocked.right as u32) << 1)
+        | ((blocked.down as u32) << 2)
+        | ((blocked.up as u32) << 3)
+}
+
+const fn get_walkability_blocked(tile_id: u32) -> SquareBounds {
+    SquareBounds {
+        left: tile_id & 1 != 0,
+        right: tile_id & (1 << 1) != 0,
+        down: tile_id & (1 << 2) != 0,
+        up: tile_id & (1 << 3) != 0,
+    }
+}
+
+fn get_property(name: &str, value: impl Into<Value>) -> Value {
+    let value: Value = value.into();
+    let property_type = match value {
+        Value::Bool(_) => "bool",
+        Value::Number(_) => "int",
+        _ => "string",
+    };
+    json!({ "name": name, "type": property_type, "value": value })
+}
+
+fn get_tile_layer(id: u32, name: &str, width: u32, height: u32, data: Vec<u32>) -> Value {
+    json!({
+        "type": "tilelayer",
+        "id": id,
+        "name": name,
+        "x": 0,
+        "y": 0,
+        "width": width,
+        "height": height,
+        "opacity": 1,
+        "visible": true,
+        "data": data,
+    })
+}
+
+fn get_tileset(first_gid: u32, name: &str, tile_count: u32, tiles: Vec<Value>) -> Value {
+    json!({
+        "firstgid": first_gid,
+        "name": name,
+        "image": format!("{}.png", name),
+        "imagewidth": tile_count * TILE_PIXELS,
+        "imageheight": TILE_PIXELS,
+        "tilewidth": TILE_PIXELS,
+        "tileheight": TILE_PIXELS,
+        "tilecount": tile_count,
+        "columns": tile_count,
+        "margin": 0,
+        "spacing": 0,
+        "tiles": tiles,
+    })
+}
+
+fn get_tileset_first_gid(map: &Value, name: &'static str) -> Result<u64, TiledMapError> {
+    map["tilesets"]
+        .as_array()
+        .ok_or(TiledMapError::InvalidField("tilesets"))?
+        .iter()
+        .find(|tileset| tileset["name"] == name)
+        .ok_or(TiledMapError::MissingTileset(name))?["firstgid"]
+        .as_u64()
+        .ok_or(TiledMapError::InvalidField("firstgid"))
+}
+
+/// Global tile IDs of a tile layer, without flip flags
+fn get_tile_layer_data(
+    map: &Value,
+    name: &'static str,
+    tiles_count: u64,
+) -> Result<Vec<u64>, TiledMapError> {
+    let layer = map["layers"]
+        .as_array()
+        .ok_or(TiledMapError::InvalidField("layers"))?
+        .iter()
+        .find(|layer| layer["type"] == "tilelayer" && layer["name"] == name)
+        .ok_or(TiledMapError::MissingLayer(name))?;
+    let data = layer["data"]
+        .as_array()
+        .filter(|data| data.len() as u64 == tiles_count)
+        .ok_or(TiledMapError::InvalidField("data"))?;
+    data.iter()
+        .map(|gid| {
+            gid.as_u64()
+                .map(|gid| gid & !TILE_FLAGS_MASK)
+                .ok_or(TiledMapError::InvalidField("data"))
+        })
+        .collect()
+}
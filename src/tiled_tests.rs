@@ -0,0 +1,72 @@
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use serde_json::Value;
+
+use effigy_shared::game::map::{MapChunkXY, MapTileVisualType, MapTileXY};
+
+use super::map_gen::MapGenerator;
+use super::tiled::{export_tiled_map, import_tiled_map, TiledMapError};
+use super::walkability::WalkabilityRules;
+
+#[test]
+fn tiled_map_round_trip() {
+    let generator = MapGenerator::new(453537, &MapChunkXY { x: 2, y: 2 })
+        .with_terrain(0.2, 0.5)
+        .with_buildings(0.1, 0.25)
+        .with_water(0.2, 0.5)
+        .with_trees(0.2, 0.5);
+    let description = generator.get_description();
+    let grid = generator.generate();
+
+    let json = export_tiled_map(&grid, Some(&description));
+    let map: Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(map["width"], grid.get_tiles_bounds().x);
+    let properties = map["properties"].as_array().unwrap();
+    assert!(properties
+        .iter()
+        .any(|property| property["name"] == "seed" && property["value"] == 453537));
+    assert!(properties.iter().any(|property| property["name"] == "steps"
+        && property["value"]
+            .as_str()
+            .unwrap()
+            .starts_with("with_terrain(0.2, 0.5)\n")));
+
+    let imported = import_tiled_map(&json, None).unwrap();
+    assert_eq!(imported.get_chunks_bounds(), grid.get_chunks_bounds());
+    let tiles_bounds = grid.get_tiles_bounds();
+    for y in 0..tiles_bounds.y {
+        for x in 0..tiles_bounds.x {
+            let tile = grid.get_tile(&MapTileXY { x, y }).unwrap();
+            let imported_tile = imported.get_tile(&MapTileXY { x, y }).unwrap();
+            assert_eq!(tile.visual_type, imported_tile.visual_type);
+            assert_eq!(tile.walkability_blocked, imported_tile.walkability_blocked);
+        }
+    }
+}
+
+#[test]
+fn import_edited_tiled_map() {
+    let grid = MapGenerator::new(453537, &MapChunkXY { x: 1, y: 1 })
+        .with_water(0.2, 0.5)
+        .generate();
+    let mut map: Value = serde_json::from_str(&export_tiled_map(&grid, None)).unwrap();
+
+    // Paint a flipped tree at (1, 0) on the tiles layer only
+    map["layers"][0]["data"][1] = Value::from(0x8000_0000u32 | 2);
+    let edited = import_tiled_map(&map.to_string(), Some(&WalkabilityRules::default())).unwrap();
+    let tree = edited.get_tile(&MapTileXY { x: 1, y: 0 }).unwrap();
+    assert_eq!(tree.visual_type, MapTileVisualType::LandTree);
+    assert!(tree.walkability_blocked.left && tree.walkability_blocked.right);
+
+    map["layers"][0]["data"][1] = Value::from(99);
+    assert!(matches!(
+        import_tiled_map(&map.to_string(), None),
+        Err(TiledMapError::InvalidTile { x: 1, y: 0, .. })
+    ));
+    map["width"] = Value::from(7);
+    assert!(matches!(
+        import_tiled_map(&map.to_string(), None),
+        Err(TiledMapError::UnalignedSize { .. })
+    ));
+}
@@ -0,0 +1,99 @@
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use effigy_shared::game::map::MapTileVisualType;
+
+/// Whether units can cross the shared edge between two neighboring tiles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeRule {
+    /// Passable in both directions
+    Open,
+    /// Passable in neither direction
+    Blocked,
+    /// Passable only from the first tile type of the pair into the second, e.g. jumping down a
+    /// ledge
+    OneWay,
+}
+
+const VISUAL_TYPES_COUNT: usize = 5;
+
+const fn get_visual_type_index(visual_type: &MapTileVisualType) -> usize {
+    match visual_type {
+        MapTileVisualType::Land => 0,
+        MapTileVisualType::LandTree => 1,
+        MapTileVisualType::Water => 2,
+        MapTileVisualType::Terrain => 3,
+        MapTileVisualType::Building => 4,
+    }
+}
+
+/// Rule table deciding which tile edges are blocked when generating the map, keyed by the pair of
+/// tile types sharing each edge.
+///
+/// A tile's side of an edge is blocked if units can't leave the tile across it, so a one-way edge
+/// is open on one side and blocked on the other.
+/// See: `MapGenerator::with_walkability_rules`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalkabilityRules {
+    /// Whether units can step from a tile of the first type into a tile of the second type
+    passable: [[bool; VISUAL_TYPES_COUNT]; VISUAL_TYPES_COUNT],
+    outer_edges_blocked: bool,
+}
+impl WalkabilityRules {
+    /// Edges are open between tiles of the same type and blocked between different types, so
+    /// every patch of a tile type is its own walkable area. Outer map edges are blocked.
+    pub fn new() -> Self {
+        let mut passable = [[false; VISUAL_TYPES_COUNT]; VISUAL_TYPES_COUNT];
+        for (index, row) in passable.iter_mut().enumerate() {
+            row[index] = true;
+        }
+        WalkabilityRules {
+            passable,
+            outer_edges_blocked: true,
+        }
+    }
+
+    /// Sets the rule for every edge between a tile of type `from` and a tile of type `to`, in
+    /// either arrangement. For `EdgeRule::OneWay`, units can only step from `from` into `to`.
+    pub fn with_edge(
+        mut self,
+        from: &MapTileVisualType,
+        to: &MapTileVisualType,
+        rule: EdgeRule,
+    ) -> Self {
+        let from_index = get_visual_type_index(from);
+        let to_index = get_visual_type_index(to);
+        assert!(
+            from_index != to_index || rule != EdgeRule::OneWay,
+            "Edges between tiles of the same type can't be one-way"
+        );
+        let (forward, backward) = match rule {
+            EdgeRule::Open => (true, true),
+            EdgeRule::Blocked => (false, false),
+            EdgeRule::OneWay => (true, false),
+        };
+        self.passable[from_index][to_index] = forward;
+        self.passable[to_index][from_index] = backward;
+        self
+    }
+
+    /// Sets whether units can walk off the outer edges of the map, e.g. onto a neighboring map
+    pub fn with_outer_edges_blocked(mut self, blocked: bool) -> Self {
+        self.outer_edges_blocked = blocked;
+        self
+    }
+
+    /// Whether units can step from a tile of type `from` into a neighboring tile of type `to`
+    pub const fn is_passable(&self, from: &MapTileVisualType, to: &MapTileVisualType) -> bool {
+        self.passable[get_visual_type_index(from)][get_visual_type_index(to)]
+    }
+
+    pub const fn are_outer_edges_blocked(&self) -> bool {
+        self.outer_edges_blocked
+    }
+}
+impl Default for WalkabilityRules {
+    fn default() -> Self {
+        WalkabilityRules::new()
+    }
+}
@@ -0,0 +1,60 @@
+#[allow(unused_imports)]
+use effigy_shared::logging::{debug, error, info, trace, warn};
+
+use effigy_shared::game::map::{MapChunkXY, MapTileVisualType, MapTileXY};
+
+use super::map_gen::MapGenerator;
+use super::pathfinding::can_step;
+use super::walkability::{EdgeRule, WalkabilityRules};
+
+#[test]
+fn generate_with_walkability_rules() {
+    let rules = WalkabilityRules::new()
+        .with_edge(
+            &MapTileVisualType::LandTree,
+            &MapTileVisualType::Land,
+            EdgeRule::OneWay,
+        )
+        .with_edge(
+            &MapTileVisualType::LandTree,
+            &MapTileVisualType::LandTree,
+            EdgeRule::Blocked,
+        )
+        .with_outer_edges_blocked(false);
+    let grid = MapGenerator::new(453537, &MapChunkXY { x: 2, y: 2 })
+        .with_trees(0.4, 0.5)
+        .with_walkability_rules(rules)
+        .generate();
+
+    let origin = grid.get_tile(&MapTileXY { x: 0, y: 0 }).unwrap();
+    assert!(!origin.walkability_blocked.left && !origin.walkability_blocked.down);
+
+    let mut one_way_count: usize = 0;
+    for y in 0..grid.get_tiles_bounds().y {
+        for x in 0..(grid.get_tiles_bounds().x - 1) {
+            let tile_xy = MapTileXY { x, y };
+            let right_xy = MapTileXY { x: x + 1, y };
+            let visual_types = (
+                &grid.get_tile(&tile_xy).unwrap().visual_type,
+                &grid.get_tile(&right_xy).unwrap().visual_type,
+            );
+            match visual_types {
+                (MapTileVisualType::LandTree, MapTileVisualType::Land) => {
+                    assert!(can_step(&grid, &tile_xy, &right_xy));
+                    assert!(!can_step(&grid, &right_xy, &tile_xy));
+                    one_way_count += 1;
+                }
+                (MapTileVisualType::LandTree, MapTileVisualType::LandTree) => {
+                    assert!(!can_step(&grid, &tile_xy, &right_xy));
+                    assert!(!can_step(&grid, &right_xy, &tile_xy));
+                }
+                (MapTileVisualType::Land, MapTileVisualType::Land) => {
+                    assert!(can_step(&grid, &tile_xy, &right_xy));
+                    assert!(can_step(&grid, &right_xy, &tile_xy));
+                }
+                _ => {}
+            }
+        }
+    }
+    assert!(one_way_count > 0, "Expected trees next to land");
+}